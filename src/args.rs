@@ -70,7 +70,7 @@ pub fn get_matches() -> ArgMatches {
                     Arg::new("openssl")
                         .short('s')
                         .long("openssl")
-                        .help("Use OpenSSL instead of Let's Encrypt for SSL certificates (optional, defaults to false). This option is applied to all apps in the project.")
+                        .help("Use OpenSSL instead of Let's Encrypt for SSL certificates of apps that do not set --tls (optional, defaults to false)")
                         .required(false)
                         .action(ArgAction::SetTrue)
                 )
@@ -94,7 +94,23 @@ pub fn get_matches() -> ArgMatches {
                     Arg::new("env")
                         .short('e')
                         .long("env")
-                        .help("Environment variables to add to the app (optional, multiple allowed per app)")
+                        .help("Environment variables to add to the app, as KEY=VALUE or a bare KEY to pass it through from the host. Values may reference variables as ${VAR} or ${VAR:-default}, resolved on every deploy (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("env-file")
+                        .short('E')
+                        .long("env-file")
+                        .help("File with environment variables for the app, one KEY=VALUE per line. Read again on every deploy (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("secret")
+                        .short('S')
+                        .long("secret")
+                        .help("Secret to inject into the app, stored with \"nbot secret set\". NAME sets the env var NAME, NAME=VAR sets the env var VAR and NAME:/path writes a file. Files keep the value out of \"docker inspect\" (optional, multiple allowed per app)")
                         .required(false)
                         .action(ArgAction::Append)
                 )
@@ -126,7 +142,135 @@ pub fn get_matches() -> ArgMatches {
                     Arg::new("domain")
                         .short('o')
                         .long("domain")
-                        .help("Domain to use for the project. Exposes the app to the internet (optional, multiple allowed per app)")
+                        .help("Domain to use for the project. Exposes the app to the internet. Use FROM->TO[,STATUS] for a domain that only redirects, e.g. www.example.com->example.com,308 (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("route")
+                        .short('u')
+                        .long("route")
+                        .help("Serve the app under a path of a domain, as DOMAIN/PATH[,port=PORT][,strip_prefix], e.g. example.com/api,port=8000,strip_prefix. Routes of all apps for a domain are merged into one server block (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("proxy")
+                        .long("proxy")
+                        .help("Set a proxy option of the app as KEY[=VALUE]: max_body_size=50m, read_timeout=300s, send_timeout=300s, buffering=on|off, websockets, request_header=NAME:VALUE, response_header=NAME:VALUE or cors=ORIGIN (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("allow")
+                        .long("allow")
+                        .help("Address or CIDR range that may access the app through nginx, e.g. 10.0.0.0/8. Everyone else is denied once any is given (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("deny")
+                        .long("deny")
+                        .help("Address or CIDR range that may not access the app through nginx (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("rate-limit")
+                        .long("rate-limit")
+                        .help("Limit the requests per client address as RATE[,burst=N][,nodelay][,path=PATH], e.g. 10r/s,burst=20,path=/login. Excess requests get 429 (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("conn-limit")
+                        .long("conn-limit")
+                        .help("Maximum number of concurrent connections per client address (optional, max 1 per app)")
+                        .value_parser(value_parser!(u32))
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("replicas")
+                        .long("replicas")
+                        .help("Number of containers running the app. nginx balances requests over them (optional, defaults to 1, max 1 per app)")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("balance")
+                        .long("balance")
+                        .help("How nginx spreads requests over the replicas: round_robin, least_conn, or ip_hash to send each client to the same replica (optional, defaults to round_robin, max 1 per app)")
+                        .value_parser(["round_robin", "least_conn", "ip_hash"])
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("health")
+                        .long("health")
+                        .help("Health check of the app: http:PATH[=STATUS] (needs curl or wget in the image), tcp (needs nc) or exec:COMMAND, followed by ,interval=, ,timeout=, ,retries= and ,start_period= options, e.g. http:/healthz,interval=5s. Deploys wait until the app is healthy (optional, max 1 per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                        .allow_hyphen_values(true)
+                )
+                .arg(
+                    Arg::new("restart")
+                        .long("restart")
+                        .help("When Docker restarts the app's containers: no, on-failure[:MAX_RETRIES], unless-stopped or always (optional, defaults to no, max 1 per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("memory")
+                        .long("memory")
+                        .help("Memory limit of each container of the app, e.g. 512m or 1g (optional, max 1 per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("memory-reservation")
+                        .long("memory-reservation")
+                        .help("Memory each container of the app gets when the host runs low, e.g. 256m (optional, max 1 per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("cpus")
+                        .long("cpus")
+                        .help("Number of CPUs each container of the app may use, e.g. 0.5 (optional, max 1 per app)")
+                        .value_parser(value_parser!(f64))
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("cpu-shares")
+                        .long("cpu-shares")
+                        .help("Relative CPU weight of the app's containers when CPUs are busy, Docker's default is 1024 (optional, max 1 per app)")
+                        .value_parser(value_parser!(u32).range(2..))
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("pids-limit")
+                        .long("pids-limit")
+                        .help("Maximum number of processes in each container of the app (optional, max 1 per app)")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("stream")
+                        .long("stream")
+                        .help("Forward a raw TCP/UDP port of the host to the app through nginx, as PORT[:APP_PORT][/tcp|/udp], e.g. 5432 or 27015/udp (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("publish")
+                        .short('P')
+                        .long("publish")
+                        .help("Publish a port of the app directly on the host, bypassing nginx, as [IP:]HOST_PORT:APP_PORT[/tcp|/udp], with ranges as START-END, e.g. 127.0.0.1:5433:5432 (optional, multiple allowed per app)")
                         .required(false)
                         .action(ArgAction::Append)
                 )
@@ -134,7 +278,30 @@ pub fn get_matches() -> ArgMatches {
                     Arg::new("email")
                         .short('m')
                         .long("email")
-                        .help("Email to use for the project (required if --domain is used)")
+                        .help("Email to use for the project (required if --domain is used with Let's Encrypt)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("tls")
+                        .short('t')
+                        .long("tls")
+                        .value_parser(["letsencrypt", "openssl", "custom", "none"])
+                        .help("How the certificate for the app's domains is obtained. \"custom\" requires --tls-cert and --tls-key, \"none\" serves the app over plain HTTP (optional, max 1 per app, defaults to letsencrypt)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("tls-cert")
+                        .long("tls-cert")
+                        .help("Path to the certificate chain (PEM) for --tls custom (optional, max 1 per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("tls-key")
+                        .long("tls-key")
+                        .help("Path to the private key (PEM) for --tls custom (optional, max 1 per app)")
                         .required(false)
                         .action(ArgAction::Append)
                 )
@@ -159,11 +326,450 @@ pub fn get_matches() -> ArgMatches {
                     Arg::new("cmd")
                         .short('c')
                         .long("cmd")
-                        .help("Command to run in the container (optional, defaults to none)")
+                        .help("Command to run in the container, quoted like in a shell, e.g. \"sh -c 'echo hello world'\" (optional, defaults to the cmd of the image)")
                         .required(false)
                         .action(ArgAction::Append)
                         .allow_hyphen_values(true)
                 )
+                .arg(
+                    Arg::new("user")
+                        .long("user")
+                        .help("User the app runs as, as USER[:GROUP] (optional, defaults to the user of the image, max 1 per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("workdir")
+                        .long("workdir")
+                        .help("Working directory of the app (optional, defaults to the one of the image, max 1 per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("entrypoint")
+                        .long("entrypoint")
+                        .help("Entrypoint to run instead of the one of the image, quoted like --cmd (optional, max 1 per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                        .allow_hyphen_values(true)
+                )
+                .arg(
+                    Arg::new("hostname")
+                        .long("hostname")
+                        .help("Hostname of the app's containers (optional, max 1 per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("label")
+                        .long("label")
+                        .help("Extra label of the app's containers, as KEY=VALUE (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("cap-add")
+                        .long("cap-add")
+                        .help("Linux capability to add, e.g. NET_ADMIN (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("cap-drop")
+                        .long("cap-drop")
+                        .help("Linux capability to drop, e.g. ALL (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("read-only")
+                        .long("read-only")
+                        .help("Mount the root filesystem of the app read-only (optional, defaults to false)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                        .value_parser(value_parser!(bool))
+                )
+                .arg(
+                    Arg::new("tmpfs")
+                        .long("tmpfs")
+                        .help("In-memory mount, as PATH[:OPTIONS], e.g. /tmp:size=64m (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("sysctl")
+                        .long("sysctl")
+                        .help("Kernel parameter of the app, as KEY=VALUE, e.g. net.core.somaxconn=1024 (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("ulimit")
+                        .long("ulimit")
+                        .help("Resource limit of the app's processes, as NAME=SOFT[:HARD], e.g. nofile=1024:2048 (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("add-host")
+                        .long("add-host")
+                        .help("Extra entry for /etc/hosts, as HOST:IP, IP may be host-gateway (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("dns")
+                        .long("dns")
+                        .help("DNS server of the app instead of Docker's (optional, multiple allowed per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+                .arg(
+                    Arg::new("shm-size")
+                        .long("shm-size")
+                        .help("Size of /dev/shm, e.g. 256m (optional, defaults to 64m, max 1 per app)")
+                        .required(false)
+                        .action(ArgAction::Append)
+                )
+        )
+        .subcommand(
+            Command::new("apply")
+                .about("Creates or updates a project from a manifest file (TOML, YAML or JSON).")
+                .arg(
+                    Arg::new("file")
+                        .value_parser(value_parser!(String))
+                        .short('f')
+                        .long("file")
+                        .help("Path to the manifest file, e.g. nbot.toml (required)")
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Force the recreation of an existing project. Hint: use in CI/CD pipeline. (optional, defaults to false)")
+                        .required(false)
+                        .action(ArgAction::SetTrue)
+                )
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Prints a stored project as a docker-compose file or a manifest")
+                .arg(
+                    Arg::new("project")
+                        .value_parser(value_parser!(String))
+                        .help("Name of the project to export (required)")
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(["compose", "toml", "yaml", "json"])
+                        .default_value("toml")
+                        .help("Output format (optional, defaults to toml)")
+                        .required(false)
+                )
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Imports a project from another format.")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(
+                    Command::new("compose")
+                        .about("Translates a docker-compose file into an nbot project. Prints the equivalent manifest unless --save is used.")
+                        .arg(
+                            Arg::new("file")
+                                .value_parser(value_parser!(String))
+                                .help("Path to the docker-compose file (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("name")
+                                .value_parser(value_parser!(String))
+                                .short('n')
+                                .long("name")
+                                .help("Name of the project (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("save")
+                                .short('s')
+                                .long("save")
+                                .help("Store the project instead of printing the manifest. Run \"nbot up\" to deploy it afterwards. (optional, defaults to false)")
+                                .required(false)
+                                .action(ArgAction::SetTrue)
+                        )
+                        .arg(
+                            Arg::new("force")
+                                .short('f')
+                                .long("force")
+                                .help("Override an existing project without asking (optional, defaults to false)")
+                                .required(false)
+                                .action(ArgAction::SetTrue)
+                        )
+                        .arg(
+                            Arg::new("format")
+                                .long("format")
+                                .value_parser(["toml", "yaml", "json"])
+                                .default_value("toml")
+                                .help("Format of the printed manifest (optional, defaults to toml)")
+                                .required(false)
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("plan")
+                .about("Shows what applying a manifest file would change, without changing anything.")
+                .arg(
+                    Arg::new("file")
+                        .value_parser(value_parser!(String))
+                        .short('f')
+                        .long("file")
+                        .help("Path to the manifest file, e.g. nbot.toml (required)")
+                        .required(true)
+                )
+        )
+        .subcommand(
+            Command::new("secret")
+                .about("Manage encrypted secrets that apps can reference with --secret.")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(
+                    Command::new("set")
+                        .about("Stores a secret. Reads the value from stdin if it is not given.")
+                        .arg(
+                            Arg::new("project")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the project (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("name")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the secret (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("value")
+                                .value_parser(value_parser!(String))
+                                .help("Value of the secret (optional, read from stdin if omitted)")
+                                .required(false)
+                        )
+                )
+                .subcommand(
+                    Command::new("get")
+                        .about("Prints the value of a secret")
+                        .arg(
+                            Arg::new("project")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the project (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("name")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the secret (required)")
+                                .required(true)
+                        )
+                )
+                .subcommand(
+                    Command::new("rm")
+                        .about("Removes a secret")
+                        .arg(
+                            Arg::new("project")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the project (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("name")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the secret (required)")
+                                .required(true)
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("auth")
+                .about("Manage the users that have to log in to reach an app through nginx (HTTP basic auth).")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Adds a user, or changes their password. Reads the password from stdin if it is not given.")
+                        .arg(
+                            Arg::new("project")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the project (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("app")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the app (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("user")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the user (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("password")
+                                .value_parser(value_parser!(String))
+                                .help("Password of the user (optional, read from stdin if omitted)")
+                                .required(false)
+                        )
+                )
+                .subcommand(
+                    Command::new("rm")
+                        .about("Removes a user. The app is public again once the last user is removed.")
+                        .arg(
+                            Arg::new("project")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the project (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("app")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the app (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("user")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the user (required)")
+                                .required(true)
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("maintenance")
+                .about("Serves a maintenance page (503) instead of an app, e.g. while it is stopped or migrated. Stays on until turned off.")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(
+                    Command::new("on")
+                        .about("Turns maintenance mode on")
+                        .arg(
+                            Arg::new("project")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the project (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("app")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the app (optional, all apps of the project if omitted)")
+                                .required(false)
+                        )
+                        .arg(
+                            Arg::new("page")
+                                .long("page")
+                                .value_parser(value_parser!(String))
+                                .help("HTML file to serve instead of the default page, which is maintenance.html in the nbot_html volume (optional)")
+                                .required(false)
+                        )
+                )
+                .subcommand(
+                    Command::new("off")
+                        .about("Turns maintenance mode off")
+                        .arg(
+                            Arg::new("project")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the project (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("app")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the app (optional, all apps of the project if omitted)")
+                                .required(false)
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("volume")
+                .about("Lists, backs up and restores the named volumes of apps.")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(
+                    Command::new("ls")
+                        .about("Lists the named volumes of the apps, with the apps using them and their size")
+                        .arg(
+                            Arg::new("project")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the project (optional, all projects if omitted)")
+                                .required(false)
+                        )
+                )
+                .subcommand(
+                    Command::new("backup")
+                        .about("Writes the contents of a volume to a tarball, gzipped if the file ends in .gz or .tgz")
+                        .arg(
+                            Arg::new("volume")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the volume (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .short('o')
+                                .long("output")
+                                .value_parser(value_parser!(String))
+                                .help("File to write, e.g. data.tar.gz (required)")
+                                .required(true)
+                        )
+                )
+                .subcommand(
+                    Command::new("restore")
+                        .about("Replaces the contents of a volume with a tarball made by \"nbot volume backup\". Creates the volume if it does not exist")
+                        .arg(
+                            Arg::new("volume")
+                                .value_parser(value_parser!(String))
+                                .help("Name of the volume (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("input")
+                                .short('i')
+                                .long("input")
+                                .value_parser(value_parser!(String))
+                                .help("Tarball to restore, e.g. data.tar.gz (required)")
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("force")
+                                .short('f')
+                                .long("force")
+                                .help("Replace the contents of an existing volume without confirmation")
+                                .action(ArgAction::SetTrue)
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("scale")
+                .about("Changes the number of containers running an app, without restarting the ones that are kept")
+                .arg(
+                    Arg::new("project")
+                        .value_parser(value_parser!(String))
+                        .help("Name of the project (required)")
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("app")
+                        .value_parser(value_parser!(String))
+                        .help("Name of the app (required)")
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("replicas")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .help("Number of replicas (required)")
+                        .required(true)
+                )
         )
         .subcommand(
             Command::new("start")
@@ -196,10 +802,59 @@ pub fn get_matches() -> ArgMatches {
                         .required(true)
                 )
         )
+        .subcommand(
+            Command::new("history")
+                .about("Lists the revisions of a project, one for every successful deploy")
+                .arg(
+                    Arg::new("project")
+                        .value_parser(value_parser!(String))
+                        .help("Name of the project (required)")
+                        .required(true)
+                )
+        )
+        .subcommand(
+            Command::new("rollback")
+                .about("Redeploys an earlier revision of a project, with the images it ran")
+                .arg(
+                    Arg::new("project")
+                        .value_parser(value_parser!(String))
+                        .help("Name of the project (required)")
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("revision")
+                        .value_parser(value_parser!(u32))
+                        .help("Revision to roll back to, as listed by \"nbot history\" (optional, defaults to the revision before the current one)")
+                        .required(false)
+                )
+                .arg(
+                    Arg::new("force")
+                        .short('f')
+                        .long("force")
+                        .help("Skip confirmation prompt (optional, defaults to false)")
+                        .action(ArgAction::SetTrue)
+                )
+        )
         .subcommand(
             Command::new("status")
                 .about("Displays the status of all projects")
         )
+        .subcommand(
+            Command::new("inspect")
+                .about("Displays the configuration of the apps in a project, such as routes, access rules and limits")
+                .arg(
+                    Arg::new("project")
+                        .value_parser(value_parser!(String))
+                        .help("Name of the project to inspect (required)")
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("app")
+                        .value_parser(value_parser!(String))
+                        .help("Name of the app to inspect (optional, all apps if omitted)")
+                        .required(false)
+                )
+        )
         .subcommand(
             Command::new("reset")
                 .about("Removes all nginx volumes (including certificates), project containers, networks and configurations. Use with caution!")
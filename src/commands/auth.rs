@@ -0,0 +1,64 @@
+use std::io::{IsTerminal, Write};
+
+use clap::ArgMatches;
+
+use crate::{configs::htpasswd::Htpasswd, APP_STATE};
+
+use super::nginx::Nginx;
+
+pub struct Auth;
+
+impl Auth {
+    pub async fn process_matches(args: &ArgMatches) {
+        let Some((command, args)) = args.subcommand() else {
+            unreachable!();
+        };
+
+        let project_name = args.get_one::<String>("project").unwrap();
+        let app_name = args.get_one::<String>("app").unwrap();
+        let user = args.get_one::<String>("user").unwrap();
+
+        let app = APP_STATE
+            .projects
+            .iter()
+            .find(|p| &p.name == project_name)
+            .and_then(|p| p.apps.iter().find(|a| &a.name == app_name));
+        let Some(app) = app else {
+            eprintln!("App not found");
+            std::process::exit(1);
+        };
+
+        let mut htpasswd = Htpasswd::from_storage(&app.container_name);
+        match command {
+            "add" => {
+                let password = match args.get_one::<String>("password") {
+                    Some(password) => password.to_owned(),
+                    None => Auth::read_password(),
+                };
+                htpasswd.set(user, &password);
+                Nginx::upload_htpasswd(&app.container_name).await;
+                Nginx::sync_domains(&app.served_domains(), &APP_STATE).await;
+            }
+            "rm" => {
+                if !htpasswd.remove(user) {
+                    eprintln!("User not found");
+                    std::process::exit(1);
+                }
+                Nginx::sync_domains(&app.served_domains(), &APP_STATE).await;
+                Nginx::upload_htpasswd(&app.container_name).await;
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn read_password() -> String {
+        if std::io::stdin().is_terminal() {
+            print!("Password: ");
+            std::io::stdout().flush().unwrap();
+        }
+
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).unwrap();
+        line.trim_end_matches(['\n', '\r']).to_owned()
+    }
+}
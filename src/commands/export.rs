@@ -0,0 +1,20 @@
+use crate::models::{Compose, Manifest, ManifestFormat, Project};
+
+pub struct Export;
+
+impl Export {
+    pub fn project(project: Project, format: &str) {
+        let rendered = match format {
+            "compose" => Compose::from_project(&project).render(),
+            format => {
+                let Some(format) = ManifestFormat::from_name(format) else {
+                    eprintln!("Unsupported export format: {}", format);
+                    std::process::exit(1);
+                };
+                Manifest::from_project(&project).render(format)
+            }
+        };
+
+        print!("{}", rendered);
+    }
+}
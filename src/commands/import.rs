@@ -0,0 +1,67 @@
+use std::{io::Write, path::Path, process};
+
+use clap::ArgMatches;
+
+use crate::{
+    models::{Compose, ManifestFormat},
+    APP_STATE,
+};
+
+pub struct Import;
+
+impl Import {
+    pub fn process_matches(args: &ArgMatches) {
+        match args.subcommand() {
+            Some(("compose", args)) => {
+                Import::compose(args);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn compose(args: &ArgMatches) {
+        let file = args.get_one::<String>("file").unwrap();
+        let name = args.get_one::<String>("name").unwrap();
+        let save = args.get_flag("save");
+        let force = args.get_flag("force");
+        let format = args
+            .get_one::<String>("format")
+            .and_then(|f| ManifestFormat::from_name(f))
+            .unwrap_or(ManifestFormat::Toml);
+
+        let base_dir = Path::new(file).parent().unwrap_or(Path::new("."));
+        let (manifest, unsupported) = Compose::from_file(file).into_manifest(name, base_dir);
+
+        if !unsupported.is_empty() {
+            eprintln!("The following compose keys cannot be represented by nbot and were skipped:");
+            for key in &unsupported {
+                eprintln!("  {key}");
+            }
+        }
+
+        // Validates the translated project even when it is only printed
+        let project = manifest.clone().into_project();
+
+        if !save {
+            print!("{}", manifest.render(format));
+            return;
+        }
+
+        let mut app_state = APP_STATE.to_owned();
+        if !force && app_state.exists(&project.name) {
+            let mut line = String::new();
+            print!("Project already exists. Override? (y/n): ");
+            std::io::stdout().flush().unwrap();
+            std::io::stdin().read_line(&mut line).unwrap();
+            if line.trim() != "y" {
+                process::exit(1);
+            }
+        }
+
+        app_state.add_or_update_project(&project);
+        println!(
+            "Project {} saved. Run \"nbot up\" to deploy it.",
+            project.name
+        );
+    }
+}
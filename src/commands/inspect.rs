@@ -0,0 +1,217 @@
+use crate::{
+    configs::{htpasswd::Htpasswd, maintenance::Maintenance},
+    docker,
+    models::{App, Memory, Project},
+    utils::shell::Shell,
+};
+
+pub struct Inspect;
+
+impl Inspect {
+    /// Prints the configuration of the apps of a project, or of one app.
+    pub async fn project(project: Project, app_name: Option<&String>) {
+        let apps: Vec<&App> = project
+            .apps
+            .iter()
+            .filter(|app| app_name.is_none_or(|name| &app.name == name))
+            .collect();
+
+        if apps.is_empty() {
+            eprintln!("App not found");
+            std::process::exit(1);
+        }
+
+        for (index, app) in apps.iter().enumerate() {
+            if index > 0 {
+                println!();
+            }
+            Inspect::app(app).await;
+        }
+    }
+
+    async fn app(app: &App) {
+        let mut statuses = vec![];
+        let mut usages = vec![];
+        for container_name in app.container_names() {
+            let mut status = match docker::containers::find_by_name(&container_name).await {
+                Some(container) => container.state.unwrap_or_default(),
+                None => "container not found".to_owned(),
+            };
+            if status == "running" {
+                usages.extend(docker::containers::usage(&container_name).await);
+            }
+            if let Some((health, _)) = docker::containers::health(&container_name).await {
+                status = format!("{} ({})", status, health);
+            }
+            statuses.push(status);
+        }
+
+        let image = match &app.image_id {
+            Some(image_id) => {
+                let id = image_id.strip_prefix("sha256:").unwrap_or(image_id);
+                format!("{} (rolled back to {})", app.image, &id[..id.len().min(12)])
+            }
+            None => app.image.to_owned(),
+        };
+        let mut lines: Vec<(&str, String)> =
+            vec![("image", image), ("status", statuses.join(", "))];
+        if app.replica_count() > 1 {
+            let balance = app.balance.unwrap_or_default();
+            let line = format!("{} ({})", app.replica_count(), balance.name());
+            lines.push(("replicas", line));
+        }
+        if Maintenance::is_on(&app.container_name) {
+            lines.push(("maintenance", "on".to_owned()));
+        }
+        if let Some(port) = &app.port {
+            lines.push(("port", port.to_owned()));
+        }
+
+        for domain in app.served_domains() {
+            for route in app.routes_for(&domain) {
+                let path = if route.path == "/" { "" } else { &route.path };
+                lines.push(("domain", format!("{}{}", domain, path)));
+            }
+        }
+        for redirect in &app.redirects {
+            let line = format!("{} -> {} ({})", redirect.from, redirect.to, redirect.status);
+            lines.push(("redirect", line));
+        }
+        for stream in &app.streams {
+            lines.push((
+                "stream",
+                format!("{} -> {}", stream.key(), stream.target_port()),
+            ));
+        }
+        for mapping in &app.publish {
+            lines.push(("publish", mapping.to_string()));
+        }
+        if let Some(tls) = app.tls {
+            lines.push(("tls", tls.name().to_owned()));
+        }
+
+        for address in &app.allow {
+            lines.push(("allow", address.to_owned()));
+        }
+        for address in &app.deny {
+            lines.push(("deny", address.to_owned()));
+        }
+        let htpasswd = Htpasswd::from_storage(&app.container_name);
+        if !htpasswd.is_empty() {
+            lines.push(("auth users", htpasswd.users().join(", ")));
+        }
+
+        for limit in &app.rate_limits {
+            lines.push(("rate limit", limit.describe()));
+        }
+        if let Some(limit) = app.conn_limit {
+            lines.push(("conn limit", format!("{} per client", limit)));
+        }
+
+        lines.extend(Inspect::resources(app, &usages));
+
+        if let Some(restart) = app.restart {
+            lines.push(("restart", restart.to_string()));
+        }
+        Inspect::push_runtime(app, &mut lines);
+        if let Some(check) = &app.health_check {
+            let check = serde_json::to_string(check).unwrap();
+            lines.push(("health check", check));
+        }
+        if !app.proxy.is_default() {
+            let proxy = serde_json::to_string(&app.proxy).unwrap();
+            lines.push(("proxy", proxy));
+        }
+
+        println!("{} ({})", app.name, app.container_name);
+        for (label, value) in lines {
+            println!("  {:<12} {}", format!("{}:", label), value);
+        }
+    }
+
+    fn push_runtime(app: &App, lines: &mut Vec<(&str, String)>) {
+        let options = [
+            ("user", &app.user),
+            ("workdir", &app.working_dir),
+            ("hostname", &app.hostname),
+        ];
+        for (label, value) in options {
+            if let Some(value) = value {
+                lines.push((label, value.to_owned()));
+            }
+        }
+
+        let commands = [("entrypoint", &app.entrypoint), ("cmd", &app.cmd)];
+        for (label, args) in commands {
+            if let Some(args) = args {
+                lines.push((label, Shell::join(args)));
+            }
+        }
+
+        let lists = [
+            ("label", &app.labels),
+            ("cap add", &app.cap_add),
+            ("cap drop", &app.cap_drop),
+            ("tmpfs", &app.tmpfs),
+            ("sysctl", &app.sysctls),
+            ("add host", &app.extra_hosts),
+            ("dns", &app.dns),
+        ];
+        for (label, values) in lists {
+            for value in values {
+                lines.push((label, value.to_owned()));
+            }
+        }
+        for ulimit in &app.ulimits {
+            lines.push(("ulimit", ulimit.to_string()));
+        }
+
+        if app.read_only {
+            lines.push(("read only", "true".to_owned()));
+        }
+        if let Some(size) = app.shm_size {
+            lines.push(("shm size", size.to_string()));
+        }
+    }
+
+    /// The usage of the running replicas against the limits of the app, e.g.
+    /// `118.4MiB, 96.0MiB / 512m`.
+    fn resources(app: &App, usages: &[docker::containers::Usage]) -> Vec<(&'static str, String)> {
+        let against = |usage: Vec<String>, limit: Option<String>| {
+            let usage = usage.join(", ");
+            match (usage.is_empty(), limit) {
+                (true, limit) => limit,
+                (false, Some(limit)) => Some(format!("{} / {}", usage, limit)),
+                (false, None) => Some(usage),
+            }
+        };
+
+        let memory = usages.iter().map(|usage| Memory::human(usage.memory));
+        let cpu = usages
+            .iter()
+            .map(|usage| format!("{:.1}%", usage.cpu_percent));
+        let pids = usages.iter().map(|usage| usage.pids.to_string());
+        // docker stats counts a fully used CPU as 100%
+        let cpus = app
+            .cpus
+            .map(|cpus| format!("{:.0}% ({} cpus)", cpus * 100.0, cpus));
+
+        let mut lines = vec![];
+        if let Some(memory) = against(memory.collect(), app.memory.map(|m| m.to_string())) {
+            lines.push(("memory", memory));
+        }
+        if let Some(reservation) = app.memory_reservation {
+            lines.push(("reservation", reservation.to_string()));
+        }
+        if let Some(cpu) = against(cpu.collect(), cpus) {
+            lines.push(("cpu", cpu));
+        }
+        if let Some(shares) = app.cpu_shares {
+            lines.push(("cpu shares", shares.to_string()));
+        }
+        if let Some(pids) = against(pids.collect(), app.pids_limit.map(|l| l.to_string())) {
+            lines.push(("pids", pids));
+        }
+        lines
+    }
+}
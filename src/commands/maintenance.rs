@@ -0,0 +1,59 @@
+use std::fs;
+
+use clap::ArgMatches;
+
+use crate::{configs::maintenance::Maintenance, models::App, APP_STATE};
+
+use super::nginx::Nginx;
+
+pub struct MaintenanceMode;
+
+impl MaintenanceMode {
+    pub async fn process_matches(args: &ArgMatches) {
+        let Some((command, args)) = args.subcommand() else {
+            unreachable!();
+        };
+
+        let project_name = args.get_one::<String>("project").unwrap();
+        let app_name = args.get_one::<String>("app");
+
+        let Some(project) = APP_STATE.projects.iter().find(|p| &p.name == project_name) else {
+            eprintln!("Project not found");
+            std::process::exit(1);
+        };
+        let apps: Vec<&App> = project
+            .apps
+            .iter()
+            .filter(|app| app_name.is_none_or(|name| &app.name == name))
+            .collect();
+        if apps.is_empty() {
+            eprintln!("App not found");
+            std::process::exit(1);
+        }
+
+        let on = command == "on";
+        let page = match args.try_get_one::<String>("page").ok().flatten() {
+            Some(path) => match fs::read_to_string(path) {
+                Ok(page) => Some(page),
+                Err(e) => {
+                    eprintln!("Error reading {}: {}", path, e);
+                    std::process::exit(1);
+                }
+            },
+            None => None,
+        };
+
+        Nginx::upload_maintenance_page(None).await;
+        let mut maintenance = Maintenance::from_storage();
+        let mut domains: Vec<String> = vec![];
+        for app in apps {
+            if let Some(page) = &page {
+                Nginx::upload_maintenance_page(Some((&app.container_name, page))).await;
+            }
+            maintenance.set(&app.container_name, on);
+            domains.extend(app.served_domains());
+        }
+
+        Nginx::sync_domains(&domains, &APP_STATE).await;
+    }
+}
@@ -1,22 +1,42 @@
 use clap::ArgMatches;
 
+mod auth;
+mod export;
+mod import;
+mod inspect;
+mod maintenance;
 mod nginx;
+mod plan;
 mod reset;
 mod rm;
+mod rollback;
 mod run;
+mod scale;
+mod secret;
 mod start;
 mod status;
 mod stop;
 mod up_down;
+mod volume;
 
+use auth::Auth;
+use export::Export;
+use import::Import;
+use inspect::Inspect;
+use maintenance::MaintenanceMode;
 use nginx::Nginx;
+use plan::Plan;
 use reset::Reset;
 use rm::Rm;
+use rollback::Rollback;
 use run::Run;
+use scale::Scale;
+use secret::Secret;
 use start::Start;
 use status::Status;
 use stop::Stop;
 use up_down::UpDown;
+use volume::Volume;
 
 use crate::models::Project;
 
@@ -36,6 +56,45 @@ pub async fn process_matches(args: ArgMatches) {
             let force = args.get_flag("force");
             Run::project(project, force).await;
         }
+        Some(("apply", args)) => {
+            let project = Project::from_cli_apply(args);
+            let force = args.get_flag("force");
+            Run::project(project, force).await;
+        }
+        Some(("export", args)) => {
+            let project = Project::from_cli_start(args);
+            let format = args.get_one::<String>("format").unwrap();
+            Export::project(project, format);
+        }
+        Some(("import", args)) => {
+            Import::process_matches(args);
+        }
+        Some(("plan", args)) => {
+            let project = Project::from_cli_apply(args);
+            let plan = Plan::new(&project).await;
+            plan.display();
+            if plan.is_empty() {
+                println!("No changes.");
+            }
+        }
+        Some(("secret", args)) => {
+            Secret::process_matches(args);
+        }
+        Some(("auth", args)) => {
+            Auth::process_matches(args).await;
+        }
+        Some(("maintenance", args)) => {
+            MaintenanceMode::process_matches(args).await;
+        }
+        Some(("volume", args)) => {
+            Volume::process_matches(args).await;
+        }
+        Some(("scale", args)) => {
+            let project = args.get_one::<String>("project").unwrap();
+            let app = args.get_one::<String>("app").unwrap();
+            let replicas = args.get_one::<u32>("replicas").unwrap();
+            Scale::app(project, app, *replicas).await;
+        }
         Some(("start", args)) => {
             let project = Project::from_cli_start(args);
             Start::project(project).await;
@@ -47,6 +106,19 @@ pub async fn process_matches(args: ArgMatches) {
         Some(("rm", args)) => {
             Rm::projects(args).await;
         }
+        Some(("inspect", args)) => {
+            let project = Project::from_cli_start(args);
+            Inspect::project(project, args.get_one::<String>("app")).await;
+        }
+        Some(("history", args)) => {
+            Rollback::history(args.get_one::<String>("project").unwrap());
+        }
+        Some(("rollback", args)) => {
+            let project = args.get_one::<String>("project").unwrap();
+            let revision = args.get_one::<u32>("revision").copied();
+            let force = args.get_flag("force");
+            Rollback::project(project, revision, force).await;
+        }
         Some(("status", _)) => {
             Status::new().await.display();
         }
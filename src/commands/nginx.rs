@@ -1,9 +1,16 @@
+use std::io::ErrorKind;
+
 use clap::ArgMatches;
 
 use crate::{
+    configs::{app_state::AppState, htpasswd::Htpasswd, maintenance::Maintenance},
     docker,
-    models::App,
-    utils::{contants::NGINX_CONTAINER_NAME, networks::Network},
+    models::{App, TlsMode},
+    nginx_files::{Limits, Site, Streams, NGINX_FUNCTIONS, NGINX_MAINTENANCE_PAGE},
+    utils::{
+        contants::{NGINX_CONTAINER_NAME, NGINX_STAGING_DIR},
+        networks::Network,
+    },
     APP_STATE,
 };
 
@@ -14,7 +21,7 @@ impl Nginx {
         match args.subcommand() {
             Some(("run", args)) => {
                 let build = args.get_flag("build");
-                Nginx::run(build).await;
+                Nginx::run(build, &APP_STATE).await;
             }
             Some(("stop", args)) => {
                 let remove = args.get_flag("remove");
@@ -24,7 +31,7 @@ impl Nginx {
         }
     }
 
-    pub async fn run(build: bool) {
+    pub async fn run(build: bool, state: &AppState) {
         let started = docker::containers::start_nginx().await;
         if started && !build {
             return;
@@ -39,17 +46,208 @@ impl Nginx {
             docker::images::build_nginx().await;
         }
 
-        docker::containers::run_nginx().await;
+        let stream_ports = Nginx::stream_ports(state);
+        Nginx::ensure_ports_are_free(&stream_ports, &[]).await;
+        docker::containers::run_nginx(&stream_ports).await;
 
         // find networks and connect to them
-        let projects = APP_STATE.to_owned().projects;
-        'project_loop: for project in projects {
-            for app in project.apps {
-                if app.domains.is_some() {
-                    let network = Network::nginx_from_project(&project.name);
-                    Nginx::connect_to_network(&network).await;
-                    continue 'project_loop;
+        for project in &state.projects {
+            if project.apps.iter().any(App::uses_nginx) {
+                let network = Network::nginx_from_project(&project.name);
+                Nginx::connect_to_network(&network).await;
+            }
+        }
+
+        Nginx::upload_streams(state).await;
+        Nginx::upload_maintenance_page(None).await;
+        for app in state.apps() {
+            if Htpasswd::exists(&app.container_name) {
+                Nginx::upload_htpasswd(&app.container_name).await;
+            }
+        }
+    }
+
+    /// Copies the basic auth users of an app into the nginx container, or
+    /// removes the file there if the app has none.
+    pub async fn upload_htpasswd(container_name: &str) {
+        let nginx_container = format!("{}{}", APP_STATE.container_prefix, NGINX_CONTAINER_NAME);
+        let htpasswd = Htpasswd::from_storage(container_name);
+        let path = Htpasswd::nginx_path(container_name);
+
+        let result = if htpasswd.is_empty() {
+            let cmd = vec!["rm", "-f", path.as_str()];
+            let (_, code, error) = docker::exec::exec(&nginx_container, &cmd).await;
+            if code == 0 {
+                Ok(())
+            } else {
+                Err(error)
+            }
+        } else {
+            let content = htpasswd.render();
+            let files = vec![(path.trim_start_matches('/'), content.as_str())];
+            docker::containers::upload(&nginx_container, files, 0o644).await
+        };
+
+        if let Err(e) = result {
+            eprintln!("Error updating users of {}", container_name);
+            eprintln!("{}", e);
+        }
+    }
+
+    /// Copies a maintenance page into the html volume of nginx. Without a
+    /// page, the default one is added unless the volume already has one, so
+    /// it can be replaced with a custom page.
+    pub async fn upload_maintenance_page(page: Option<(&str, &str)>) {
+        let nginx_container = format!("{}{}", APP_STATE.container_prefix, NGINX_CONTAINER_NAME);
+        let (path, content) = match page {
+            Some((container_name, content)) => {
+                let path = format!(
+                    "/usr/share/nginx/html{}",
+                    Maintenance::page_path(container_name)
+                );
+                (path, content)
+            }
+            None => {
+                let path = "/usr/share/nginx/html/maintenance.html";
+                let (_, code, _) =
+                    docker::exec::exec(&nginx_container, &["test", "-f", path]).await;
+                if code == 0 {
+                    return;
                 }
+                (path.to_owned(), NGINX_MAINTENANCE_PAGE)
+            }
+        };
+
+        let files = vec![(path.trim_start_matches('/'), content)];
+        if let Err(e) = docker::containers::upload(&nginx_container, files, 0o644).await {
+            eprintln!("Error uploading maintenance page");
+            eprintln!("{}", e);
+        }
+    }
+
+    /// Regenerates the stream config from the app state. The nginx container
+    /// is recreated if the host ports it has to publish have changed.
+    pub async fn sync_streams(state: &AppState) {
+        let name = format!("{}{}", APP_STATE.container_prefix, NGINX_CONTAINER_NAME);
+        let Some(published) = docker::containers::find_published_ports(&name).await else {
+            return;
+        };
+
+        let mut published: Vec<String> = published
+            .into_iter()
+            .filter(|key| key != "80/tcp" && key != "443/tcp")
+            .collect();
+        published.sort();
+
+        let mut stream_ports = Nginx::stream_ports(state);
+        stream_ports.sort();
+
+        let outdated = Nginx::lacks_upstream_resolve(&name, state).await;
+        if published == stream_ports && !outdated {
+            Nginx::upload_streams(state).await;
+            return;
+        }
+
+        Nginx::ensure_ports_are_free(&stream_ports, &published).await;
+
+        // Images built before streams were supported lack the stream block
+        let build = outdated || (!stream_ports.is_empty() && !Nginx::supports_streams(&name).await);
+
+        if published == stream_ports {
+            println!("Rebuilding nginx to balance over replicas");
+        } else {
+            println!(
+                "Recreating nginx to publish ports: {}",
+                stream_ports.join(", ")
+            );
+        }
+        Nginx::stop(true).await;
+        Nginx::run(build, state).await;
+    }
+
+    /// Exits the process if a host port of the streams in `state` that nginx
+    /// does not publish yet is taken. Checked before the state is saved, so a
+    /// conflicting stream never ends up in it.
+    pub async fn check_stream_ports(state: &AppState) {
+        let name = format!("{}{}", APP_STATE.container_prefix, NGINX_CONTAINER_NAME);
+        let published = docker::containers::find_published_ports(&name)
+            .await
+            .unwrap_or_default();
+        Nginx::ensure_ports_are_free(&Nginx::stream_ports(state), &published).await;
+    }
+
+    async fn upload_streams(state: &AppState) {
+        let nginx_container = format!("{}{}", APP_STATE.container_prefix, NGINX_CONTAINER_NAME);
+        let streams = Streams::from_state(state);
+        let path = format!("etc/nginx/stream.d/{}", streams.file_name());
+        let changes = vec![(path, Some(streams.render()))];
+        if let Err(e) = Nginx::apply_confs(&nginx_container, &changes).await {
+            eprintln!("Error adding stream conf");
+            eprintln!("{}", e);
+        }
+    }
+
+    async fn supports_streams(container: &str) -> bool {
+        let cmd = vec!["grep", "-q", "stream.d", "/etc/nginx/nginx.conf"];
+        let (_, code, _) = docker::exec::exec(container, &cmd).await;
+        code == 0
+    }
+
+    /// True if `state` balances over replicas, but the image of the running
+    /// nginx predates upstreams that resolve their servers at runtime.
+    async fn lacks_upstream_resolve(container: &str, state: &AppState) -> bool {
+        let balanced = state
+            .apps()
+            .any(|app| app.uses_nginx() && state.upstream_hosts(app).len() > 1);
+        if !balanced {
+            return false;
+        }
+
+        // nginx -v prints e.g. "nginx version: nginx/1.27.3" to stderr
+        let (out, code, error) = docker::exec::exec(container, &["nginx", "-v"]).await;
+        if code != 0 {
+            return false;
+        }
+        let output = format!("{}{}", out, error);
+        let version: Vec<u32> = output
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .split('.')
+            .map_while(|part| part.parse().ok())
+            .collect();
+        version < vec![1, 27, 3]
+    }
+
+    /// The host ports nginx has to publish for streams, as `PORT/PROTOCOL`.
+    fn stream_ports(state: &AppState) -> Vec<String> {
+        state
+            .streams()
+            .iter()
+            .map(|(_, stream)| stream.key())
+            .collect()
+    }
+
+    /// Exits the process if any of `ports` that nginx does not already
+    /// publish is taken by another container or a process on the host.
+    async fn ensure_ports_are_free(ports: &[String], published: &[String]) {
+        let name = format!("{}{}", APP_STATE.container_prefix, NGINX_CONTAINER_NAME);
+        let in_use = docker::containers::published_host_ports(&name).await;
+
+        for key in ports.iter().filter(|key| !published.contains(key)) {
+            let (port, protocol) = key.split_once('/').unwrap_or((key, "tcp"));
+            let port: u16 = port.parse().unwrap_or_default();
+            let address = ("0.0.0.0", port);
+            let bound = match protocol {
+                "udp" => std::net::UdpSocket::bind(address).map(|_| ()),
+                _ => std::net::TcpListener::bind(address).map(|_| ()),
+            };
+            let taken_on_host = bound.is_err_and(|e| e.kind() == ErrorKind::AddrInUse);
+
+            if in_use.contains(key) || taken_on_host {
+                eprintln!("Error: Port {} is already in use on the host", key);
+                std::process::exit(1);
             }
         }
     }
@@ -120,66 +318,195 @@ impl Nginx {
         false
     }
 
-    pub async fn add_conf(app: &App) {
-        if app.domains.is_none() {
-            return;
-        }
-
-        let domains = app
-            .domains
-            .as_ref()
-            .expect("domains not found when adding conf");
-        if domains.is_empty() {
-            return;
+    /// Applies changed confs right away, instead of waiting for the watcher
+    /// in the container to pick them up.
+    pub async fn reload() {
+        let name = format!("{}{}", APP_STATE.container_prefix, NGINX_CONTAINER_NAME);
+        let (_, code, error) = docker::exec::exec(&name, &["nginx", "-s", "reload"]).await;
+        if code != 0 {
+            eprintln!("Error reloading nginx");
+            eprintln!("{}", error);
         }
+    }
 
-        let file_name = &domains.first().unwrap();
-        let container_name = &app.container_name;
+    /// Regenerates the server blocks of the given domains from the app state,
+    /// together with the zones of the limits the blocks refer to. Domains
+    /// that are no longer served by any app have their conf removed.
+    pub async fn sync_domains(domains: &[String], state: &AppState) {
         let nginx_container = format!("{}{}", APP_STATE.container_prefix, NGINX_CONTAINER_NAME);
-        let port = match &app.port {
-            Some(port) => port,
-            None => "80",
-        };
+        if Nginx::lacks_upstream_resolve(&nginx_container, state).await {
+            println!("Rebuilding nginx to balance over replicas");
+            Nginx::stop(true).await;
+            Nginx::run(true, state).await;
+        }
 
-        let mut cmd = vec![
-            "sh",
-            "/functions.sh",
-            "add_conf",
-            file_name.as_str(),
-            container_name,
-            port,
-        ];
-        cmd.extend(domains.iter().map(|d| d.as_str()));
+        let limits = Limits::from_state(state);
+        let mut changes = vec![(
+            format!("etc/nginx/conf.d/{}", limits.file_name()),
+            Some(limits.render()),
+        )];
+        for domain in domains {
+            match Site::from_state(domain, state) {
+                Some(site) => changes.push((
+                    format!("etc/nginx/conf.d/{}", site.file_name()),
+                    Some(site.render()),
+                )),
+                None => changes.push((format!("etc/nginx/conf.d/{}.conf", domain), None)),
+            }
+        }
 
-        docker::exec::exec(&nginx_container, &cmd).await;
+        if let Err(e) = Nginx::apply_confs(&nginx_container, &changes).await {
+            eprintln!("Error updating conf of {}", domains.join(", "));
+            eprintln!("{}", e);
+        }
     }
 
-    pub async fn remove_conf(app: &App) {
-        if app.domains.is_none() {
-            return;
+    /// Uploads conf files into a copy of the config outside of the watched
+    /// directories, or removes them there if they have no content, and
+    /// validates that copy with `nginx -t`. Only a valid set of files is
+    /// moved into place, so nginx never picks up an invalid config, also not
+    /// halfway through files that depend on each other. If a file cannot be
+    /// moved, the files moved before it are restored.
+    async fn apply_confs(
+        container: &str,
+        changes: &[(String, Option<String>)],
+    ) -> Result<(), String> {
+        // Includes of the watched directories point into the copy instead
+        let stage = format!(
+            "rm -rf {staging} && mkdir -p {staging}/conf.d {staging}/stream.d \
+             && cp -a /etc/nginx/conf.d/. {staging}/conf.d/ \
+             && {{ [ ! -d /etc/nginx/stream.d ] || cp -a /etc/nginx/stream.d/. {staging}/stream.d/; }} \
+             && sed -e 's#/etc/nginx/conf.d/#{staging}/conf.d/#' \
+                -e 's#/etc/nginx/stream.d/#{staging}/stream.d/#' \
+                /etc/nginx/nginx.conf > {staging}/nginx.conf",
+            staging = NGINX_STAGING_DIR
+        );
+        let (_, code, error) = docker::exec::exec(container, &["sh", "-c", &stage]).await;
+        if code != 0 {
+            return Err(format!("Error staging config:\n{}", error));
         }
 
-        let domains = app.domains.as_ref().unwrap();
-        if domains.is_empty() {
-            return;
+        let staged_path =
+            |path: &str| format!("/{}", path).replacen("/etc/nginx", NGINX_STAGING_DIR, 1);
+        let staged: Vec<(String, &str)> = changes
+            .iter()
+            .filter_map(|(path, conf)| Some((staged_path(path), conf.as_deref()?)))
+            .collect();
+        if !staged.is_empty() {
+            let files = staged
+                .iter()
+                .map(|(path, conf)| (path.trim_start_matches('/'), *conf))
+                .collect();
+            docker::containers::upload(container, files, 0o644).await?;
         }
 
-        let file_name = &domains.first().unwrap();
-        let cmd = vec!["sh", "/functions.sh", "remove_conf", file_name.as_str()];
-        let name = format!("{}{}", APP_STATE.container_prefix, NGINX_CONTAINER_NAME);
-        let (_, code, error) = docker::exec::exec(&name, &cmd).await;
+        let removed: Vec<String> = changes
+            .iter()
+            .filter(|(_, conf)| conf.is_none())
+            .map(|(path, _)| staged_path(path))
+            .collect();
+        if !removed.is_empty() {
+            let mut cmd = vec!["rm", "-f"];
+            cmd.extend(removed.iter().map(String::as_str));
+            let (_, code, error) = docker::exec::exec(container, &cmd).await;
+            if code != 0 {
+                return Err(format!("Error staging config:\n{}", error));
+            }
+        }
 
+        let staged_conf = format!("{}/nginx.conf", NGINX_STAGING_DIR);
+        let cmd = vec!["nginx", "-t", "-c", staged_conf.as_str()];
+        let (_, code, error) = docker::exec::exec(container, &cmd).await;
         if code != 0 {
-            eprintln!("Error removing conf");
-            eprintln!("{}", error);
+            return Err(format!("Invalid config, nothing was changed:\n{}", error));
+        }
+
+        // Copies of the live files, put back if a move fails so nginx is not
+        // left with half of the new config
+        let backup_dir = format!("{}/backup", NGINX_STAGING_DIR);
+        let live_paths: Vec<String> = changes
+            .iter()
+            .map(|(path, _)| format!("/{}", path))
+            .collect();
+        let backup = "backup=\"$1\"; shift; for live; do \
+                      [ ! -f \"$live\" ] || { mkdir -p \"$backup$(dirname \"$live\")\" \
+                      && cp -a \"$live\" \"$backup$live\"; } || exit 1; done";
+        let mut cmd = vec!["sh", "-c", backup, "sh", backup_dir.as_str()];
+        cmd.extend(live_paths.iter().map(String::as_str));
+        let (_, code, error) = docker::exec::exec(container, &cmd).await;
+        if code != 0 {
+            return Err(format!(
+                "Error backing up config, nothing was changed:\n{}",
+                error
+            ));
+        }
+
+        for (index, ((path, conf), live_path)) in changes.iter().zip(&live_paths).enumerate() {
+            let (_, code, error) = match conf {
+                Some(_) => {
+                    // Moving across volumes is a copy, so the file is copied
+                    // under a name nginx does not include and renamed once
+                    // complete
+                    let (directory, file_name) =
+                        live_path.rsplit_once('/').unwrap_or(("", live_path));
+                    let partial_path = format!("{}/.{}.partial", directory, file_name);
+                    let staged_path = staged_path(path);
+                    let cmd = vec![
+                        "sh",
+                        "-c",
+                        "mv \"$1\" \"$2\" && mv \"$2\" \"$3\"",
+                        "sh",
+                        staged_path.as_str(),
+                        partial_path.as_str(),
+                        live_path.as_str(),
+                    ];
+                    docker::exec::exec(container, &cmd).await
+                }
+                None => docker::exec::exec(container, &["rm", "-f", live_path]).await,
+            };
+            if code != 0 {
+                let mut message = format!("Error moving {} into place:\n{}", live_path, error);
+                match Nginx::restore_confs(container, &backup_dir, &live_paths[..=index]).await {
+                    Ok(()) => message.push_str("\nThe previous config was restored"),
+                    Err(e) => {
+                        message.push_str(&format!("\nError restoring the previous config:\n{}", e))
+                    }
+                }
+                return Err(message);
+            }
         }
+
+        Ok(())
     }
 
-    pub async fn generate_certificates(app: &App) {
-        if app.domains.is_none() {
+    /// Puts back the files at `live_paths` from their copies in `backup_dir`,
+    /// and removes those that had no copy as they did not exist before.
+    async fn restore_confs(
+        container: &str,
+        backup_dir: &str,
+        live_paths: &[String],
+    ) -> Result<(), String> {
+        let restore = "backup=\"$1\"; shift; status=0; for live; do \
+                       if [ -f \"$backup$live\" ]; then \
+                       partial=\"$(dirname \"$live\")/.$(basename \"$live\").partial\"; \
+                       { cp -a \"$backup$live\" \"$partial\" && mv \"$partial\" \"$live\"; } || status=1; \
+                       else rm -f \"$live\" || status=1; fi; done; exit $status";
+        let mut cmd = vec!["sh", "-c", restore, "sh", backup_dir];
+        cmd.extend(live_paths.iter().map(String::as_str));
+        let (_, code, error) = docker::exec::exec(container, &cmd).await;
+        if code != 0 {
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    /// Obtains the certificate for the domains `app` is responsible for.
+    pub async fn generate_certificates(app: &App, state: &AppState) {
+        let domains = state.owned_domains(app);
+        if domains.is_empty() {
             return;
         }
-        let domains = app.domains.as_ref().unwrap();
         let name = format!("{}{}", APP_STATE.container_prefix, NGINX_CONTAINER_NAME);
         let container = docker::containers::find_by_name(&name).await;
         let Some(container) = container else {
@@ -189,26 +516,70 @@ impl Nginx {
             return;
         };
 
-        let use_openssl = app.openssl.unwrap_or(false);
-
-        let cmd = if use_openssl {
-            vec![
-                "sh",
-                "/functions.sh",
-                "generate_certs_openssl",
-                domains.first().unwrap(),
-            ]
-        } else {
-            let email = app.email.as_ref().unwrap();
-            let mut cmd = vec!["sh", "/functions.sh", "generate_certs_certbot", email];
-            cmd.extend(domains.iter().map(|d| d.as_str()));
-            cmd
+        let cmd = match app.tls.unwrap_or(TlsMode::Letsencrypt) {
+            TlsMode::Letsencrypt => {
+                let email = app.email.as_ref().unwrap();
+                let mut cmd = vec!["sh", "/functions.sh", "generate_certs_certbot", email];
+                cmd.extend(domains.iter().map(|d| d.as_str()));
+                cmd
+            }
+            // One certificate covers every domain, including redirect sources
+            TlsMode::Openssl => {
+                let mut cmd = vec!["sh", "/functions.sh", "generate_certs_openssl"];
+                cmd.extend(domains.iter().map(|d| d.as_str()));
+                cmd
+            }
+            TlsMode::Custom => {
+                let domain = domains.first().unwrap();
+                Nginx::upload_custom_certificates(app, domain, container_id.as_str()).await;
+                return;
+            }
+            TlsMode::None => return,
         };
 
+        // The script is baked into the image, containers of older images get
+        // the current one
+        let files = vec![("functions.sh", NGINX_FUNCTIONS)];
+        if let Err(e) = docker::containers::upload(container_id.as_str(), files, 0o755).await {
+            eprintln!("Error updating certificate script");
+            eprintln!("{}", e);
+        }
+
         let (_, code, error) = docker::exec::exec(container_id.as_str(), &cmd).await;
         if code != 0 {
             eprintln!("Error generating certificate");
             eprintln!("{}", error);
         }
     }
+
+    /// Copies the user provided certificate and key into the nginx container,
+    /// where nginx expects them for the app's first owned domain.
+    async fn upload_custom_certificates(app: &App, domain: &str, container_id: &str) {
+        let (Some(cert_path), Some(key_path)) = (&app.tls_cert, &app.tls_key) else {
+            return;
+        };
+
+        let read = |path: &str| match std::fs::read_to_string(path) {
+            Ok(content) => Some(content),
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path, e);
+                None
+            }
+        };
+        let (Some(cert), Some(key)) = (read(cert_path), read(key_path)) else {
+            return;
+        };
+
+        let cert_file = format!("etc/letsencrypt/live/{}/fullchain.pem", domain);
+        let key_file = format!("etc/letsencrypt/live/{}/privkey.pem", domain);
+        let files = vec![
+            (cert_file.as_str(), cert.as_str()),
+            (key_file.as_str(), key.as_str()),
+        ];
+
+        if let Err(e) = docker::containers::upload(container_id, files, 0o600).await {
+            eprintln!("Error uploading certificate");
+            eprintln!("{}", e);
+        }
+    }
 }
@@ -0,0 +1,280 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+use crate::{
+    docker,
+    models::{App, Project},
+    utils::networks::Network,
+    APP_STATE,
+};
+
+#[derive(Debug, PartialEq)]
+enum AppAction {
+    Create,
+    Recreate,
+    Start,
+    Remove,
+    Unchanged,
+}
+
+impl AppAction {
+    fn symbol(&self) -> &'static str {
+        match self {
+            AppAction::Create => "+",
+            AppAction::Recreate => "~",
+            AppAction::Start => ">",
+            AppAction::Remove => "-",
+            AppAction::Unchanged => "=",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            AppAction::Create => "create",
+            AppAction::Recreate => "recreate",
+            AppAction::Start => "start",
+            AppAction::Remove => "remove",
+            AppAction::Unchanged => "unchanged",
+        }
+    }
+}
+
+struct AppPlan {
+    name: String,
+    action: AppAction,
+    reason: Option<String>,
+    changes: Vec<String>,
+}
+
+/// Describes what deploying a project would change, compared to the stored
+/// project definition and the containers that are currently running.
+pub struct Plan {
+    project: String,
+    apps: Vec<AppPlan>,
+    networks: Vec<String>,
+    nginx: Vec<String>,
+}
+
+impl Plan {
+    pub async fn new(project: &Project) -> Self {
+        let stored = APP_STATE
+            .projects
+            .iter()
+            .find(|p| p.name == project.name)
+            .cloned();
+        let stored_apps = stored.map(|p| p.apps).unwrap_or_default();
+
+        let mut apps = vec![];
+        for app in &project.apps {
+            let stored_app = stored_apps.iter().find(|a| a.name == app.name);
+            apps.push(Self::plan_app(stored_app, app).await);
+        }
+
+        for stored_app in &stored_apps {
+            if !project.apps.iter().any(|a| a.name == stored_app.name) {
+                apps.push(AppPlan {
+                    name: stored_app.name.to_owned(),
+                    action: AppAction::Remove,
+                    reason: None,
+                    changes: vec![],
+                });
+            }
+        }
+
+        let mut networks = vec![];
+        for network in [
+            Network::internal_from_project(&project.name),
+            Network::nginx_from_project(&project.name),
+        ] {
+            let name = match &network {
+                Network::Internal(name) => name,
+                Network::Nginx(name) => name,
+            };
+            if !docker::network::exists(name).await {
+                networks.push(format!("+ {name}"));
+            }
+        }
+
+        let nginx = Self::plan_nginx(project, &stored_apps, &apps);
+
+        Self {
+            project: project.name.to_owned(),
+            apps,
+            networks,
+            nginx,
+        }
+    }
+
+    /// True if deploying the project would not change anything.
+    pub fn is_empty(&self) -> bool {
+        self.networks.is_empty()
+            && self.nginx.is_empty()
+            && self
+                .apps
+                .iter()
+                .all(|app| app.action == AppAction::Unchanged)
+    }
+
+    pub fn display(&self) {
+        println!("\nProject: {}\n", self.project);
+
+        println!("Apps:");
+        for app in &self.apps {
+            let reason = match &app.reason {
+                Some(reason) => format!(", {reason}"),
+                None => String::new(),
+            };
+            println!(
+                "  {} {} ({}{})",
+                app.action.symbol(),
+                app.name,
+                app.action.label(),
+                reason
+            );
+            for change in &app.changes {
+                println!("      {change}");
+            }
+        }
+
+        if !self.networks.is_empty() {
+            println!("\nNetworks:");
+            for network in &self.networks {
+                println!("  {network}");
+            }
+        }
+
+        if !self.nginx.is_empty() {
+            println!("\nNginx:");
+            for conf in &self.nginx {
+                println!("  {conf}");
+            }
+        }
+
+        println!();
+    }
+
+    async fn plan_app(stored_app: Option<&App>, app: &App) -> AppPlan {
+        let changes = match stored_app {
+            Some(stored_app) => Self::diff_apps(stored_app, app),
+            None => vec![],
+        };
+
+        let container = docker::containers::find_by_name(&app.container_name).await;
+        let (action, reason) = match container {
+            None => (AppAction::Create, None),
+            Some(_) if !changes.is_empty() => (AppAction::Recreate, None),
+            Some(_) if !app.is_using_latest_image().await => (
+                AppAction::Recreate,
+                Some("container is not using the latest image".to_owned()),
+            ),
+            Some(_) if app.has_outdated_env().await => (
+                AppAction::Recreate,
+                Some("resolved environment differs from the container".to_owned()),
+            ),
+            Some(container) if container.state.as_deref() != Some("running") => (
+                AppAction::Start,
+                container.state.map(|state| format!("container is {state}")),
+            ),
+            Some(_) => (AppAction::Unchanged, None),
+        };
+
+        AppPlan {
+            name: app.name.to_owned(),
+            action,
+            reason,
+            changes,
+        }
+    }
+
+    /// Compares two app definitions field by field. Fields holding lists are
+    /// compared item by item.
+    fn diff_apps(old: &App, new: &App) -> Vec<String> {
+        let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+            (serde_json::to_value(old), serde_json::to_value(new))
+        else {
+            return vec![];
+        };
+
+        let mut changes = vec![];
+        for (field, new_value) in &new {
+            let old_value = old.get(field).unwrap_or(&Value::Null);
+            if old_value == new_value {
+                continue;
+            }
+
+            match (old_value, new_value) {
+                (Value::Array(old_items), Value::Array(new_items)) => {
+                    let count = changes.len();
+                    for item in old_items.iter().filter(|i| !new_items.contains(i)) {
+                        changes.push(format!("{field}: - {item}"));
+                    }
+                    for item in new_items.iter().filter(|i| !old_items.contains(i)) {
+                        changes.push(format!("{field}: + {item}"));
+                    }
+                    if changes.len() == count {
+                        changes.push(format!("{field}: reordered"));
+                    }
+                }
+                _ => changes.push(format!("{field}: {old_value} -> {new_value}")),
+            }
+        }
+        changes
+    }
+
+    fn plan_nginx(project: &Project, stored_apps: &[App], plans: &[AppPlan]) -> Vec<String> {
+        fn domains(apps: &[App]) -> BTreeSet<String> {
+            apps.iter().flat_map(App::served_domains).collect()
+        }
+
+        let old = domains(stored_apps);
+        let apps = &project.apps;
+        let new = domains(apps);
+
+        // Routes of other projects keep a domain's conf alive
+        let served_elsewhere = |domain: &str| {
+            APP_STATE.apps().any(|app| {
+                app.project_name() != project.name
+                    && app.served_domains().iter().any(|d| d == domain)
+            })
+        };
+
+        let mut changes = vec![];
+        for domain in old.difference(&new) {
+            match served_elsewhere(domain) {
+                true => changes.push(format!("~ {domain}.conf")),
+                false => changes.push(format!("- {domain}.conf")),
+            }
+        }
+        for domain in new.difference(&old) {
+            changes.push(format!("+ {domain}.conf"));
+        }
+        let streams = |apps: &[App]| -> BTreeSet<String> {
+            apps.iter()
+                .flat_map(|app| app.streams.iter().map(|stream| stream.key()))
+                .collect()
+        };
+        let (old_streams, new_streams) = (streams(stored_apps), streams(apps));
+        for key in old_streams.difference(&new_streams) {
+            changes.push(format!("- stream {key}"));
+        }
+        for key in new_streams.difference(&old_streams) {
+            changes.push(format!("+ stream {key}"));
+        }
+
+        for domain in old.intersection(&new) {
+            let changed = apps
+                .iter()
+                .filter(|app| app.served_domains().contains(domain))
+                .any(|app| {
+                    plans
+                        .iter()
+                        .any(|p| p.name == app.name && p.action != AppAction::Unchanged)
+                });
+            if changed {
+                changes.push(format!("~ {domain}.conf"));
+            }
+        }
+        changes
+    }
+}
@@ -1,6 +1,11 @@
 use clap::ArgMatches;
 
-use crate::{utils::networks::Network, APP_STATE};
+use crate::{
+    configs::{history::History, htpasswd::Htpasswd, maintenance::Maintenance},
+    models::App,
+    utils::networks::Network,
+    APP_STATE,
+};
 
 use super::nginx::Nginx;
 
@@ -27,15 +32,30 @@ impl Rm {
             }
         }
 
+        let mut history = History::from_storage();
+        let mut maintenance = Maintenance::from_storage();
+        let mut domains: Vec<String> = vec![];
+        for project in &projects_to_remove {
+            domains.extend(project.apps.iter().flat_map(App::served_domains));
+        }
+
         for project in projects_to_remove {
             for app in &project.apps {
-                Nginx::remove_conf(app).await;
-                if app.is_running().await {
-                    app.stop().await;
-                }
+                // Stops every replica, also when only some of them run
+                app.stop().await;
                 app.remove().await;
+
+                if Htpasswd::exists(&app.container_name) {
+                    Htpasswd::from_storage(&app.container_name).clear();
+                    Nginx::upload_htpasswd(&app.container_name).await;
+                }
+                if Maintenance::is_on(&app.container_name) {
+                    maintenance.set(&app.container_name, false);
+                }
             }
 
+            history.remove(&project.name);
+
             let project_net = Network::internal_from_project(&project.name);
             let nginx_net = Network::nginx_from_project(&project.name);
             Nginx::disconnect_from_network(&nginx_net).await;
@@ -45,5 +65,9 @@ impl Rm {
 
         state.projects = projects_to_keep;
         state.save();
+
+        // Other projects may still serve some of the domains
+        Nginx::sync_domains(&domains, &state).await;
+        Nginx::sync_streams(&state).await;
     }
 }
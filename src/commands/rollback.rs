@@ -0,0 +1,94 @@
+use tabled::{Table, Tabled};
+
+use crate::{configs::history::History, docker};
+
+use super::run::Run;
+
+#[derive(Tabled)]
+struct RevisionRow {
+    revision: String,
+    deployed: String,
+    images: String,
+}
+
+pub struct Rollback;
+
+impl Rollback {
+    /// Prints the revisions of a project, oldest first.
+    pub fn history(project_name: &str) {
+        let history = History::from_storage();
+        let revisions = history.revisions(project_name);
+        if revisions.is_empty() {
+            println!("No revisions of {}", project_name);
+            return;
+        }
+
+        let mut rows = vec![];
+        for (index, revision) in revisions.iter().enumerate() {
+            let mut images = vec![];
+            for app in &revision.project.apps {
+                let id = revision.images.get(&app.name).map(|id| {
+                    let id = id.strip_prefix("sha256:").unwrap_or(id);
+                    format!(" ({})", &id[..id.len().min(12)])
+                });
+                images.push(format!(
+                    "{}: {}{}",
+                    app.name,
+                    app.image,
+                    id.unwrap_or_default()
+                ));
+            }
+
+            let current = index == revisions.len() - 1;
+            rows.push(RevisionRow {
+                revision: match current {
+                    true => format!("{} (current)", revision.number),
+                    false => revision.number.to_string(),
+                },
+                deployed: format!("{} UTC", revision.date()),
+                images: images.join("\n"),
+            });
+        }
+        println!("{}", Table::new(rows));
+    }
+
+    /// Redeploys an earlier revision of a project, by default the one before
+    /// the current one. Its apps run the images that revision ran, by ID, so
+    /// image references other projects share are left alone.
+    pub async fn project(project_name: &str, revision: Option<u32>, force: bool) {
+        let history = History::from_storage();
+        let target = match revision {
+            Some(number) => history.revision(project_name, number),
+            None => history.revisions(project_name).iter().rev().nth(1),
+        };
+        let Some(target) = target else {
+            match revision {
+                Some(number) => eprintln!("Revision {} of {} not found", number, project_name),
+                None => eprintln!("No earlier revision of {}", project_name),
+            }
+            std::process::exit(1);
+        };
+
+        let mut project = target.project.to_owned();
+        for app in &mut project.apps {
+            app.image_id = None;
+            let Some(image_id) = target.images.get(&app.name) else {
+                continue;
+            };
+            if docker::images::exists(image_id).await {
+                app.image_id = Some(image_id.to_owned());
+            } else {
+                eprintln!(
+                    "Warning: The image {} ran by {} is no longer available, using the current {}",
+                    image_id, app.name, app.image
+                );
+            }
+        }
+
+        println!(
+            "Rolling back {} to revision {}",
+            project_name, target.number
+        );
+        Run::project(project, force).await;
+    }
+}
@@ -1,13 +1,20 @@
-use std::{io::Write, process, thread::sleep};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    process,
+    thread::sleep,
+    time::{Duration, Instant},
+};
 
 use crate::{
+    configs::{app_state::AppState, history::History},
     docker,
-    models::{App, Project},
-    utils::networks::Network,
+    models::{App, HealthCheck, Project},
+    utils::{contants::NGINX_CONTAINER_NAME, networks::Network},
     APP_STATE,
 };
 
-use super::nginx::Nginx;
+use super::{nginx::Nginx, plan::Plan};
 
 pub struct Run;
 
@@ -15,8 +22,10 @@ impl Run {
     pub async fn project(project: Project, force: bool) {
         let mut app_state = APP_STATE.to_owned();
         if !force && app_state.exists(&project.name) {
+            Plan::new(&project).await.display();
+
             let mut line = String::new();
-            print!("Project already exists. Override? (y/n): ");
+            print!("Project already exists. Apply these changes? (y/n): ");
             std::io::stdout().flush().unwrap();
             std::io::stdin().read_line(&mut line).unwrap();
             if line.trim() != "y" {
@@ -31,12 +40,36 @@ impl Run {
         };
 
         // Update app_state and save
-        app_state.add_or_update_project(&project);
+        app_state.replace_project(&project);
+        app_state.validate_routes();
+        app_state.validate_streams();
+        app_state.validate_published_ports();
+        Nginx::check_stream_ports(&app_state).await;
+        app_state.save();
 
         if !Nginx::is_running().await {
-            Nginx::run(false).await;
+            Nginx::run(false, &app_state).await;
+        }
+        Nginx::sync_streams(&app_state).await;
+
+        // Remove apps that are no longer part of the project
+        for old_app in &project_clone.apps {
+            if !project.apps.iter().any(|a| a.name == old_app.name) {
+                old_app.stop().await;
+                old_app.remove().await;
+            }
         }
 
+        // Update confs for domains that the project no longer serves
+        let served: Vec<String> = project.apps.iter().flat_map(App::served_domains).collect();
+        let stale: Vec<String> = project_clone
+            .apps
+            .iter()
+            .flat_map(App::served_domains)
+            .filter(|domain| !served.contains(domain))
+            .collect();
+        Nginx::sync_domains(&stale, &app_state).await;
+
         let networks = (
             Network::internal_from_project(&project.name).create().await,
             Network::nginx_from_project(&project.name).create().await,
@@ -45,6 +78,7 @@ impl Run {
         Nginx::connect_to_network(&networks.1).await;
 
         let apps = App::topological_sort_by_dependenceis(&project.apps);
+        let mut failed = false;
         for app in &apps {
             let app_clone = match project_clone.apps.iter().find(|a| a.name == app.name) {
                 Some(a) => a.clone(),
@@ -52,21 +86,44 @@ impl Run {
             };
 
             let force_container_rebuild = &app_clone.ne(app);
+            let app_networks = vec![&networks.0, &networks.1];
 
-            let started = app
-                .run(&vec![&networks.0, &networks.1], force_container_rebuild)
-                .await;
-            sleep(std::time::Duration::from_secs(1));
+            // Web apps that are serving traffic are replaced without an outage,
+            // unless both versions would need the same host ports
+            let is_serving = app.is_public()
+                && app.publish.is_empty()
+                && docker::containers::find_by_name(&app.container_name)
+                    .await
+                    .is_some_and(|c| c.state.as_deref() == Some("running"));
+            if is_serving && (*force_container_rebuild || app.needs_redeploy().await) {
+                if let Err(reason) =
+                    Run::switch_over(app, &app_clone, &app_networks, &app_state).await
+                {
+                    eprintln!("{}: failed, {}", app.name, reason);
+                    failed = true;
+                    continue;
+                }
+            } else {
+                app.remove_surplus_replicas(&app_clone).await;
+                let started = app.run(&app_networks, force_container_rebuild).await;
+                sleep(std::time::Duration::from_secs(1));
 
-            if !started {
-                println!("{}: failed", app.name);
-                continue;
+                if !started {
+                    println!("{}: failed", app.name);
+                    failed = true;
+                    continue;
+                }
             }
 
             let mut up = false;
             let mut reason = String::new();
 
-            if app.domains.is_some() {
+            if let Some(check) = &app.health_check {
+                match Run::wait_until_healthy(check, &app.container_names()).await {
+                    Ok(()) => up = true,
+                    Err(e) => reason = e,
+                }
+            } else if app.is_public() {
                 // wait until container is up
                 for seconds in 1..15 {
                     // pinging a container immediately after starting it
@@ -78,50 +135,195 @@ impl Run {
                         app.start().await;
                     }
 
-                    let url = if let Some(port) = &app.port {
-                        format!("http://{}:{}", &app.container_name, port)
-                    } else {
-                        format!("http://{}", &app.container_name)
-                    };
-                    let cmd = vec!["curl", "-I", url.as_str()];
-                    // format!("curl -I http://{}", &app.container_name)
-
-                    let name = format!("{}nginx", app_state.container_prefix);
-                    let (output, code, error) = docker::exec::exec(&name, &cmd).await;
-
-                    // TODO: check result
-
-                    reason = if error.is_empty() { output } else { error };
-
-                    if code == 0 {
-                        up = true;
-                        break;
+                    match Run::responds(app, &app.container_names()).await {
+                        Ok(()) => {
+                            up = true;
+                            break;
+                        }
+                        Err(e) => reason = e,
                     }
                 }
-                Nginx::generate_certificates(app).await;
-                Nginx::add_conf(app).await;
             } else {
                 // check if container is up
                 for seconds in 1..3 {
                     sleep(std::time::Duration::from_secs(seconds));
 
-                    let container = docker::containers::find_by_name(&app.container_name).await;
-                    if let Some(container) = container {
-                        if let Some(state) = &container.state {
-                            if state == "running" {
-                                up = true;
-                                break;
-                            }
-                        }
+                    if app.is_running().await {
+                        up = true;
+                        break;
                     }
                 }
             }
 
+            if app.is_public() {
+                Nginx::generate_certificates(app, &app_state).await;
+                Nginx::sync_domains(&app.served_domains(), &app_state).await;
+            }
+
             if !up {
                 eprintln!("{}: failed. Reason: {}", app.name, reason);
                 println!("Note: If the service takes a long time to spin up, it may not in fact be failing. Run nbot status to check the status of the container.");
+                failed = true;
                 continue;
             }
         }
+
+        if !failed {
+            Run::record_revision(&project).await;
+        }
+    }
+
+    /// Checks once that every given container of a web app answers HTTP
+    /// requests from the nginx container.
+    async fn responds(app: &App, container_names: &[String]) -> Result<(), String> {
+        let nginx = format!("{}{}", APP_STATE.container_prefix, NGINX_CONTAINER_NAME);
+        for container_name in container_names {
+            let url = match &app.port {
+                Some(port) => format!("http://{}:{}", container_name, port),
+                None => format!("http://{}", container_name),
+            };
+            let cmd = vec!["curl", "-I", url.as_str()];
+            let (output, code, error) = docker::exec::exec(&nginx, &cmd).await;
+            if code != 0 {
+                return Err(if error.is_empty() { output } else { error });
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits until Docker reports every given container of an app as
+    /// healthy. Fails as soon as one is unhealthy, with the output of its
+    /// latest check.
+    async fn wait_until_healthy(
+        check: &HealthCheck,
+        container_names: &[String],
+    ) -> Result<(), String> {
+        let deadline = Instant::now() + check.max_wait();
+        loop {
+            let mut healthy = true;
+            for container_name in container_names {
+                match docker::containers::health(container_name).await {
+                    Some((status, _)) if status == "healthy" => {}
+                    Some((status, output)) if status == "unhealthy" => {
+                        return Err(format!("{} is unhealthy: {}", container_name, output));
+                    }
+                    Some(_) => healthy = false,
+                    None => return Err(format!("{} has no health status", container_name)),
+                }
+            }
+
+            if healthy {
+                return Ok(());
+            }
+            if Instant::now() > deadline {
+                return Err("timed out waiting for the health check".to_owned());
+            }
+            sleep(Duration::from_secs(1));
+        }
+    }
+
+    /// Replaces the running containers of a web app (blue/green). The new
+    /// containers start next to the old ones under temporary names, and
+    /// nginx is only switched to them once they respond. The old containers
+    /// are then removed and the new ones take over their names. If the new
+    /// containers never respond, they are removed and the old ones keep
+    /// serving.
+    async fn switch_over(
+        app: &App,
+        previous: &App,
+        networks: &Vec<&Network>,
+        state: &AppState,
+    ) -> Result<(), String> {
+        let container_names = app.container_names();
+        let next_names: Vec<String> = container_names.iter().map(|n| App::next_name(n)).collect();
+        let remove_next = || async {
+            for next_name in &next_names {
+                docker::containers::stop(next_name).await;
+                docker::containers::remove(next_name).await;
+            }
+        };
+
+        // Leftovers of a redeploy that was interrupted
+        remove_next().await;
+
+        for next_name in &next_names {
+            if !app.run_container(next_name, networks).await {
+                remove_next().await;
+                return Err(
+                    "the running version keeps serving. Reason: could not start the new containers"
+                        .to_owned(),
+                );
+            }
+        }
+
+        let ready = match &app.health_check {
+            Some(check) => Run::wait_until_healthy(check, &next_names).await,
+            None => {
+                let mut ready = Err(String::new());
+                for seconds in 1..15 {
+                    sleep(std::time::Duration::from_secs(seconds));
+                    ready = Run::responds(app, &next_names).await;
+                    if ready.is_ok() {
+                        break;
+                    }
+                }
+                ready
+            }
+        };
+        if let Err(reason) = ready {
+            remove_next().await;
+            return Err(format!(
+                "the running version keeps serving. Reason: {}",
+                reason
+            ));
+        }
+
+        let mut switching = state.clone();
+        switching.switching.push(app.container_name.to_owned());
+        Nginx::sync_domains(&app.served_domains(), &switching).await;
+        Nginx::sync_streams(&switching).await;
+        Nginx::reload().await;
+
+        for container_name in previous.container_names() {
+            docker::containers::stop(&container_name).await;
+            docker::containers::remove(&container_name).await;
+        }
+        // nginx keeps the resolved addresses until it is pointed back at the
+        // original names, which now belong to the new containers. If one
+        // cannot take over its name, nginx stays on the next names, which
+        // the renamed ones take back.
+        let mut renamed = vec![];
+        for (next_name, container_name) in next_names.iter().zip(&container_names) {
+            if !docker::containers::rename(next_name, container_name).await {
+                for (next_name, container_name) in renamed {
+                    docker::containers::rename(container_name, next_name).await;
+                }
+                return Err(format!(
+                    "the new version keeps serving as {}. Reason: could not rename {} to {}",
+                    next_names.join(", "),
+                    next_name,
+                    container_name
+                ));
+            }
+            renamed.push((next_name, container_name));
+        }
+
+        Nginx::sync_domains(&app.served_domains(), state).await;
+        Nginx::sync_streams(state).await;
+        Nginx::reload().await;
+        Ok(())
+    }
+
+    /// Adds the deployed project to its history, with the images its apps
+    /// are running.
+    async fn record_revision(project: &Project) {
+        let mut images = BTreeMap::new();
+        for app in &project.apps {
+            let container = docker::containers::find_by_name(&app.container_name).await;
+            if let Some(image_id) = container.and_then(|c| c.image_id) {
+                images.insert(app.name.to_owned(), image_id);
+            }
+        }
+        History::from_storage().record(project, images);
     }
 }
@@ -0,0 +1,43 @@
+use crate::{models::App, utils::networks::Network, APP_STATE};
+
+use super::nginx::Nginx;
+
+pub struct Scale;
+
+impl Scale {
+    /// Changes the number of replicas of a running app. Replicas that are
+    /// kept are left untouched, nginx is updated before surplus replicas are
+    /// removed.
+    pub async fn app(project_name: &String, app_name: &String, replicas: u32) {
+        let mut state = APP_STATE.to_owned();
+        let Some(project) = state.projects.iter_mut().find(|p| &p.name == project_name) else {
+            eprintln!("Project not found");
+            std::process::exit(1);
+        };
+        let Some(index) = project.apps.iter().position(|a| &a.name == app_name) else {
+            eprintln!("App not found");
+            std::process::exit(1);
+        };
+
+        let previous = project.apps[index].clone();
+        project.apps[index].replicas = (replicas > 1).then_some(replicas);
+        App::validate(&project.apps);
+        let app = project.apps[index].clone();
+        state.save();
+
+        let networks = (
+            Network::internal_from_project(project_name),
+            Network::nginx_from_project(project_name),
+        );
+        if !app.run(&vec![&networks.0, &networks.1], &false).await {
+            eprintln!("{}: failed to start replicas", app.name);
+            std::process::exit(1);
+        }
+
+        Nginx::sync_domains(&app.served_domains(), &state).await;
+        Nginx::sync_streams(&state).await;
+        app.remove_surplus_replicas(&previous).await;
+
+        println!("{}: {} replica(s)", app.name, app.replica_count());
+    }
+}
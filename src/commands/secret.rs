@@ -0,0 +1,54 @@
+use std::io::{IsTerminal, Write};
+
+use clap::ArgMatches;
+
+use crate::configs::secrets::Secrets;
+
+pub struct Secret;
+
+impl Secret {
+    pub fn process_matches(args: &ArgMatches) {
+        let Some((command, args)) = args.subcommand() else {
+            unreachable!();
+        };
+
+        let project = args.get_one::<String>("project").unwrap();
+        let name = args.get_one::<String>("name").unwrap();
+        let mut secrets = Secrets::from_storage();
+
+        match command {
+            "set" => {
+                let value = match args.get_one::<String>("value") {
+                    Some(value) => value.to_owned(),
+                    None => Secret::read_value(),
+                };
+                secrets.set(project, name, &value);
+            }
+            "get" => {
+                let Some(value) = secrets.get(project, name) else {
+                    eprintln!("Secret not found");
+                    std::process::exit(1);
+                };
+                println!("{}", value);
+            }
+            "rm" => {
+                if !secrets.remove(project, name) {
+                    eprintln!("Secret not found");
+                    std::process::exit(1);
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn read_value() -> String {
+        if std::io::stdin().is_terminal() {
+            print!("Value: ");
+            std::io::stdout().flush().unwrap();
+        }
+
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).unwrap();
+        line.trim_end_matches(['\n', '\r']).to_owned()
+    }
+}
@@ -19,52 +19,80 @@ impl AppStatus {
     pub async fn from_apps(apps: Vec<App>, project: &String) -> Vec<Self> {
         let mut app_statuses = vec![];
         for app in apps {
-            let (_, status) = Self::get_app_status(&app).await;
-
-            let domains = match app.domains {
-                Some(domains) => {
-                    let mut domains_str = String::new();
-                    for domain in domains {
-                        let linebreak = if domains_str.is_empty() { "" } else { "\n" };
-                        domains_str.push_str(&format!("{linebreak}{domain}"));
-                    }
-                    domains_str
+            let mut domains = String::new();
+            for domain in app.served_domains() {
+                for route in app.routes_for(&domain) {
+                    let linebreak = if domains.is_empty() { "" } else { "\n" };
+                    let path = if route.path == "/" { "" } else { &route.path };
+                    domains.push_str(&format!("{linebreak}{domain}{path}"));
                 }
+            }
+            for redirect in &app.redirects {
+                let linebreak = if domains.is_empty() { "" } else { "\n" };
+                domains.push_str(&format!(
+                    "{linebreak}{} -> {} ({})",
+                    redirect.from, redirect.to, redirect.status
+                ));
+            }
+
+            let certificate = match app.tls {
+                Some(tls) => tls.name().to_owned(),
                 None => String::new(),
             };
 
-            let certificate = match app.openssl {
-                Some(openssl) => if openssl { "openssl" } else { "letsencrypt" }.to_owned(),
-                None => String::new(),
-            };
-
-            let port = match app.port {
+            let mut port = match &app.port {
                 Some(port) => port.to_string(),
                 None => "".to_owned(),
             };
-
-            app_statuses.push(AppStatus {
-                project: project.to_owned(),
-                service: app.name,
-                container_name: app.container_name,
-                port,
-                status,
-                domains,
-                image: app.image,
-                certificate,
-            });
+            for stream in &app.streams {
+                let linebreak = if port.is_empty() { "" } else { "\n" };
+                match stream.target_port {
+                    Some(target) => {
+                        port.push_str(&format!("{linebreak}{} -> {target}", stream.key()))
+                    }
+                    None => port.push_str(&format!("{linebreak}{}", stream.key())),
+                }
+            }
+            for mapping in &app.publish {
+                let linebreak = if port.is_empty() { "" } else { "\n" };
+                port.push_str(&format!("{linebreak}{mapping}"));
+            }
+
+            // One row per replica, the app's settings only on the first one
+            for (index, container_name) in app.container_names().into_iter().enumerate() {
+                let (_, status) = Self::get_app_status(&container_name).await;
+                let first = index == 0;
+                let setting = |value: &String| match first {
+                    true => value.to_owned(),
+                    false => String::new(),
+                };
+
+                app_statuses.push(AppStatus {
+                    project: project.to_owned(),
+                    service: app.name.to_owned(),
+                    container_name,
+                    port: setting(&port),
+                    status,
+                    domains: setting(&domains),
+                    image: setting(&app.image),
+                    certificate: setting(&certificate),
+                });
+            }
         }
         app_statuses
     }
 
-    async fn get_app_status(app: &App) -> (String, String) {
-        let container = docker::containers::find_by_name(&app.container_name).await;
+    async fn get_app_status(container_name: &str) -> (String, String) {
+        let container = docker::containers::find_by_name(container_name).await;
         let Some(container) = container else {
             return (String::new(), "container not found".to_owned());
         };
 
         let mut container_id = container.id.unwrap();
-        let status = container.state.unwrap();
+        let mut status = container.state.unwrap();
+        if let Some((health, _)) = docker::containers::health(container_name).await {
+            status = format!("{} ({})", status, health);
+        }
 
         container_id.truncate(12);
         container_id += "...";
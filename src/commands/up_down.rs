@@ -9,7 +9,7 @@ impl UpDown {
         let state = APP_STATE.to_owned();
 
         if !Nginx::is_running().await {
-            Nginx::run(false).await;
+            Nginx::run(false, &APP_STATE).await;
         }
 
         for project in state.projects {
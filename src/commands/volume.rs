@@ -0,0 +1,166 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::Write,
+    process,
+};
+
+use clap::ArgMatches;
+use tabled::{Table, Tabled};
+
+use crate::{docker, models::Memory, APP_STATE};
+
+#[derive(Tabled)]
+struct VolumeRow {
+    volume: String,
+    apps: String,
+    size: String,
+}
+
+pub struct Volume;
+
+impl Volume {
+    pub async fn process_matches(args: &ArgMatches) {
+        match args.subcommand() {
+            Some(("ls", args)) => Volume::ls(args.get_one::<String>("project")).await,
+            Some(("backup", args)) => {
+                let volume = args.get_one::<String>("volume").unwrap();
+                let output = args.get_one::<String>("output").unwrap();
+                Volume::backup(volume, output).await;
+            }
+            Some(("restore", args)) => {
+                let volume = args.get_one::<String>("volume").unwrap();
+                let input = args.get_one::<String>("input").unwrap();
+                Volume::restore(volume, input, args.get_flag("force")).await;
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Prints the named volumes of the apps, with the apps using them and
+    /// their size on disk.
+    async fn ls(project_name: Option<&String>) {
+        let projects: Vec<_> = APP_STATE
+            .projects
+            .iter()
+            .filter(|p| project_name.is_none_or(|name| &p.name == name))
+            .collect();
+        if project_name.is_some() && projects.is_empty() {
+            eprintln!("Project not found");
+            process::exit(1);
+        }
+
+        let mut volumes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for project in projects {
+            for app in &project.apps {
+                for volume in &app.volumes {
+                    // Bind mounts are paths on the host, not volumes
+                    let source = volume.split(':').next().unwrap_or_default();
+                    if volume.contains(':') && !source.starts_with(['/', '.']) {
+                        let user = format!("{}/{}", project.name, app.name);
+                        volumes.entry(source.to_owned()).or_default().push(user);
+                    }
+                }
+            }
+        }
+        if volumes.is_empty() {
+            println!("No volumes");
+            return;
+        }
+
+        let sizes = docker::volumes::sizes().await;
+        let rows: Vec<VolumeRow> = volumes
+            .into_iter()
+            .map(|(volume, apps)| {
+                let size = match sizes.get(&volume) {
+                    Some(size) if *size >= 0 => Memory::human(*size as u64),
+                    Some(_) => "unknown".to_owned(),
+                    None => "not created".to_owned(),
+                };
+                VolumeRow {
+                    volume,
+                    apps: apps.join("\n"),
+                    size,
+                }
+            })
+            .collect();
+        println!("{}", Table::new(rows));
+    }
+
+    /// Writes the contents of a volume to a tarball, gzipped if the file
+    /// name ends in .gz or .tgz.
+    async fn backup(volume: &str, output: &str) {
+        if !docker::volumes::exists(volume).await {
+            eprintln!("Volume {} not found", volume);
+            process::exit(1);
+        }
+
+        let users = docker::volumes::used_by_running(volume).await;
+        if !users.is_empty() {
+            println!(
+                "Warning: {} is in use by {}, stop it first for a consistent backup",
+                volume,
+                users.join(", ")
+            );
+        }
+
+        let mut file = match File::create(output) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Error creating {}: {}", output, e);
+                process::exit(1);
+            }
+        };
+
+        let gzip = output.ends_with(".gz") || output.ends_with(".tgz");
+        if let Err(e) = docker::volumes::backup(volume, &mut file, gzip).await {
+            let _ = fs::remove_file(output);
+            eprintln!("Error backing up {}: {}", volume, e);
+            process::exit(1);
+        }
+        println!("Backed up {} to {}", volume, output);
+    }
+
+    /// Replaces the contents of a volume with a tarball made by `backup`.
+    /// The volume is created if it does not exist.
+    async fn restore(volume: &str, input: &str, force: bool) {
+        let tarball = match fs::read(input) {
+            Ok(tarball) => tarball,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", input, e);
+                process::exit(1);
+            }
+        };
+
+        let users = docker::volumes::used_by_running(volume).await;
+        if !users.is_empty() {
+            eprintln!(
+                "Error: {} is in use by {}, stop it first",
+                volume,
+                users.join(", ")
+            );
+            process::exit(1);
+        }
+
+        if !docker::volumes::exists(volume).await {
+            if let Err(e) = docker::volumes::create(volume).await {
+                eprintln!("Error creating volume {}: {}", volume, e);
+                process::exit(1);
+            }
+        } else if !force {
+            let mut line = String::new();
+            print!("Replace the contents of {} with {}? (y/n): ", volume, input);
+            std::io::stdout().flush().unwrap();
+            std::io::stdin().read_line(&mut line).unwrap();
+            if line.trim() != "y" {
+                process::exit(1);
+            }
+        }
+
+        if let Err(e) = docker::volumes::restore(volume, tarball).await {
+            eprintln!("Error restoring {}: {}", volume, e);
+            process::exit(1);
+        }
+        println!("Restored {} from {}", volume, input);
+    }
+}
@@ -2,7 +2,10 @@ use serde::{Deserialize, Serialize};
 use serde_json;
 use std::{fs, io::Write, process};
 
-use crate::{models::Project, utils::dirs::Dirs};
+use crate::{
+    models::{App, PortMapping, Project, Protocol, Stream},
+    utils::dirs::Dirs,
+};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AppState {
@@ -10,6 +13,10 @@ pub struct AppState {
     pub image_prefix: String,
     pub network_prefix: String,
     pub projects: Vec<Project>,
+    /// Container names of the apps whose traffic nginx currently passes to
+    /// their next containers, while a redeploy switches over.
+    #[serde(skip)]
+    pub switching: Vec<String>,
 }
 
 impl AppState {
@@ -19,6 +26,7 @@ impl AppState {
             image_prefix: String::from("nbot_"),
             network_prefix: String::from("nbot_"),
             projects: vec![],
+            switching: vec![],
         }
     }
     pub fn from_storage() -> Self {
@@ -49,13 +57,183 @@ impl AppState {
     }
 
     pub fn add_or_update_project(&mut self, project: &Project) {
+        self.replace_project(project);
+        self.save();
+    }
+
+    /// Adds or updates a project without saving the state.
+    pub fn replace_project(&mut self, project: &Project) {
         let project = project.clone();
         if let Some(existing_project) = self.projects.iter_mut().find(|p| p.name == project.name) {
             existing_project.apps = project.apps;
         } else {
             self.projects.push(project);
         }
-        self.save();
+    }
+
+    pub fn apps(&self) -> impl Iterator<Item = &App> {
+        self.projects.iter().flat_map(|p| p.apps.iter())
+    }
+
+    /// Names of the containers nginx passes the traffic of `app` to.
+    pub fn upstream_hosts(&self, app: &App) -> Vec<String> {
+        let container_names = app.container_names();
+        match self.switching.contains(&app.container_name) {
+            true => container_names
+                .iter()
+                .map(|name| App::next_name(name))
+                .collect(),
+            false => container_names,
+        }
+    }
+
+    /// The app responsible for the certificate of a domain: the first app
+    /// that lists it as one of its domains, or else the first app that
+    /// routes or redirects it.
+    pub fn domain_owner(&self, domain: &str) -> Option<&App> {
+        let lists_domain = |app: &&App| {
+            app.domains
+                .as_ref()
+                .is_some_and(|domains| domains.iter().any(|d| d == domain))
+        };
+
+        self.apps()
+            .find(lists_domain)
+            .or_else(|| {
+                self.apps()
+                    .find(|app| app.routes.iter().any(|r| r.domain == domain))
+            })
+            .or_else(|| self.apps().find(|app| app.redirect_for(domain).is_some()))
+    }
+
+    /// The served domains of `app` that it is the owner of. The certificate
+    /// covering them is named after the first one.
+    pub fn owned_domains(&self, app: &App) -> Vec<String> {
+        app.served_domains()
+            .into_iter()
+            .filter(|domain| {
+                self.domain_owner(domain)
+                    .is_some_and(|owner| owner.container_name == app.container_name)
+            })
+            .collect()
+    }
+
+    /// Exits the process if two apps, possibly in different projects, serve
+    /// the same path of a domain, or if a redirecting domain is also served.
+    pub fn validate_routes(&self) {
+        let mut served: Vec<(String, String, &App)> = vec![];
+        for app in self.apps() {
+            for redirect in &app.redirects {
+                let other_app = self.apps().find(|other_app| {
+                    !other_app.routes_for(&redirect.from).is_empty()
+                        || (other_app.container_name != app.container_name
+                            && other_app.redirect_for(&redirect.from).is_some())
+                });
+                if let Some(other_app) = other_app {
+                    eprintln!(
+                        "Error: {} is redirected by {} and also used by {}",
+                        redirect.from, app.container_name, other_app.container_name
+                    );
+                    process::exit(1);
+                }
+            }
+
+            for domain in app.served_domains() {
+                for route in app.routes_for(&domain) {
+                    let taken = served
+                        .iter()
+                        .find(|(d, p, _)| *d == domain && *p == route.path);
+                    if let Some((_, _, other_app)) = taken {
+                        eprintln!(
+                            "Error: {}{} is served by both {} and {}",
+                            domain, route.path, other_app.container_name, app.container_name
+                        );
+                        process::exit(1);
+                    }
+                    served.push((domain.to_owned(), route.path, app));
+                }
+            }
+        }
+    }
+
+    /// Every stream of every app, sorted by port.
+    pub fn streams(&self) -> Vec<(&App, &Stream)> {
+        let mut streams: Vec<(&App, &Stream)> = self
+            .apps()
+            .flat_map(|app| app.streams.iter().map(move |stream| (app, stream)))
+            .collect();
+        streams.sort_by_key(|(_, stream)| (stream.port, stream.protocol));
+        streams
+    }
+
+    /// Exits the process if two apps listen on the same port, or if an app
+    /// listens on one of the ports nginx uses for HTTP(S).
+    pub fn validate_streams(&self) {
+        let streams = self.streams();
+        for (index, (app, stream)) in streams.iter().enumerate() {
+            if stream.protocol == Protocol::Tcp && [80, 443].contains(&stream.port) {
+                eprintln!(
+                    "Error: Port {} of {} is reserved for HTTP(S) traffic",
+                    stream.port, app.container_name
+                );
+                process::exit(1);
+            }
+
+            let taken = streams[..index]
+                .iter()
+                .find(|(_, other)| other.key() == stream.key());
+            if let Some((other_app, _)) = taken {
+                eprintln!(
+                    "Error: Port {} is used by both {} and {}",
+                    stream.key(),
+                    other_app.container_name,
+                    app.container_name
+                );
+                process::exit(1);
+            }
+        }
+    }
+
+    /// Every port that an app publishes on the host, in the order of the
+    /// projects.
+    pub fn published_ports(&self) -> Vec<(&App, &PortMapping)> {
+        self.apps()
+            .flat_map(|app| app.publish.iter().map(move |mapping| (app, mapping)))
+            .collect()
+    }
+
+    /// Exits the process if two apps publish the same host port, or if an
+    /// app publishes a port nginx listens on for HTTP(S) or streams.
+    pub fn validate_published_ports(&self) {
+        let mut nginx_ports = vec![(80, Protocol::Tcp), (443, Protocol::Tcp)];
+        nginx_ports.extend(self.streams().iter().map(|(_, s)| (s.port, s.protocol)));
+
+        let published = self.published_ports();
+        for (index, (app, mapping)) in published.iter().enumerate() {
+            let nginx_port = nginx_ports
+                .iter()
+                .find(|(port, protocol)| mapping.takes(*port, *protocol));
+            if let Some((port, protocol)) = nginx_port {
+                eprintln!(
+                    "Error: Port {}/{} of {} is used by nginx",
+                    port,
+                    protocol.name(),
+                    app.container_name
+                );
+                process::exit(1);
+            }
+
+            let taken = published[..index]
+                .iter()
+                .find(|(_, other)| other.overlaps(mapping));
+            if let Some((other_app, other)) = taken {
+                eprintln!(
+                    "Error: Ports {} of {} and {} of {} overlap on the host",
+                    other, other_app.container_name, mapping, app.container_name
+                );
+                process::exit(1);
+            }
+        }
     }
 
     pub fn save(&self) {
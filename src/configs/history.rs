@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{models::Project, utils::dirs::Dirs};
+
+/// A successful deploy of a project: its full definition and the images its
+/// apps were running.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Revision {
+    pub number: u32,
+    /// Seconds since the Unix epoch.
+    pub deployed_at: u64,
+    pub project: Project,
+    /// Image ID per app name.
+    pub images: BTreeMap<String, String>,
+}
+
+impl Revision {
+    /// The deploy time as `YYYY-MM-DD HH:MM` in UTC.
+    pub fn date(&self) -> String {
+        let days = (self.deployed_at / 86400) as i64;
+        let minutes = (self.deployed_at % 86400) / 60;
+
+        // Civil date from days since the epoch, after Howard Hinnant
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            year,
+            month,
+            day,
+            minutes / 60,
+            minutes % 60
+        )
+    }
+}
+
+/// The deploy history of every project, oldest revision first.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct History {
+    projects: BTreeMap<String, Vec<Revision>>,
+}
+
+impl History {
+    /// Revisions kept per project. Older ones are dropped.
+    const MAX_REVISIONS: usize = 20;
+
+    pub fn from_storage() -> Self {
+        let Ok(content) = fs::read_to_string(Dirs::history_file()) else {
+            return Self::default();
+        };
+
+        match serde_json::from_str(&content) {
+            Ok(history) => history,
+            Err(e) => {
+                eprintln!("History file is invalid: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    fn save(&self) {
+        let content = serde_json::to_string(&self).unwrap();
+        if let Err(e) = fs::write(Dirs::history_file(), content) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+
+    pub fn revisions(&self, project: &str) -> &[Revision] {
+        self.projects.get(project).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn revision(&self, project: &str, number: u32) -> Option<&Revision> {
+        self.revisions(project).iter().find(|r| r.number == number)
+    }
+
+    /// Records a deploy as a new revision, unless nothing changed since the
+    /// latest one, e.g. when `nbot up` redeploys every project.
+    pub fn record(&mut self, project: &Project, images: BTreeMap<String, String>) {
+        let revisions = self.projects.entry(project.name.to_owned()).or_default();
+        if let Some(latest) = revisions.last() {
+            let unchanged = serde_json::to_value(&latest.project).ok()
+                == serde_json::to_value(project).ok()
+                && latest.images == images;
+            if unchanged {
+                return;
+            }
+        }
+
+        let number = revisions.last().map(|r| r.number + 1).unwrap_or(1);
+        let deployed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        revisions.push(Revision {
+            number,
+            deployed_at,
+            project: project.to_owned(),
+            images,
+        });
+        if revisions.len() > Self::MAX_REVISIONS {
+            revisions.remove(0);
+        }
+        self.save();
+    }
+
+    pub fn remove(&mut self, project: &str) {
+        if self.projects.remove(project).is_some() {
+            self.save();
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::{fs, process};
+
+use crate::utils::dirs::Dirs;
+
+/// The basic auth users of an app, stored as an htpasswd file with bcrypt
+/// hashes. A copy is uploaded to the auth volume of the nginx container.
+pub struct Htpasswd {
+    container_name: String,
+    users: Vec<(String, String)>,
+}
+
+impl Htpasswd {
+    pub fn from_storage(container_name: &str) -> Self {
+        let content = fs::read_to_string(Self::path(container_name)).unwrap_or_default();
+        let users = content
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(user, hash)| (user.to_owned(), hash.to_owned()))
+            .collect();
+
+        Self {
+            container_name: container_name.to_owned(),
+            users,
+        }
+    }
+
+    /// True if basic auth is enabled for the app.
+    pub fn exists(container_name: &str) -> bool {
+        !Self::from_storage(container_name).users.is_empty()
+    }
+
+    /// Where the file is found inside the nginx container.
+    pub fn nginx_path(container_name: &str) -> String {
+        format!("/etc/nginx/auth/{}.htpasswd", container_name)
+    }
+
+    fn path(container_name: &str) -> String {
+        format!("{}/{}.htpasswd", Dirs::auth_dir(), container_name)
+    }
+
+    pub fn save(&self) {
+        let path = Self::path(&self.container_name);
+        let result = if self.users.is_empty() {
+            fs::remove_file(&path).or_else(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => Ok(()),
+                _ => Err(e),
+            })
+        } else {
+            fs::write(&path, self.render())
+        };
+
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+
+    /// Adds a user, or changes the password of an existing one.
+    pub fn set(&mut self, user: &str, password: &str) {
+        if user.is_empty() || user.contains(':') {
+            eprintln!("Error: User names cannot be empty or contain ':'");
+            process::exit(1);
+        }
+
+        // $2y$ is the variant htpasswd writes, and that nginx understands
+        let hash = match bcrypt::hash_with_result(password, bcrypt::DEFAULT_COST) {
+            Ok(hash) => hash.format_for_version(bcrypt::Version::TwoY),
+            Err(e) => {
+                eprintln!("Error hashing password: {}", e);
+                process::exit(1);
+            }
+        };
+
+        match self.users.iter_mut().find(|(name, _)| name == user) {
+            Some(existing) => existing.1 = hash,
+            None => self.users.push((user.to_owned(), hash)),
+        }
+        self.save();
+    }
+
+    pub fn remove(&mut self, user: &str) -> bool {
+        let count = self.users.len();
+        self.users.retain(|(name, _)| name != user);
+        let removed = self.users.len() != count;
+        self.save();
+        removed
+    }
+
+    /// Removes every user, e.g. when the app itself is removed.
+    pub fn clear(&mut self) {
+        self.users.clear();
+        self.save();
+    }
+
+    pub fn users(&self) -> Vec<&str> {
+        self.users.iter().map(|(user, _)| user.as_str()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+
+    pub fn render(&self) -> String {
+        self.users
+            .iter()
+            .map(|(user, hash)| format!("{}:{}\n", user, hash))
+            .collect()
+    }
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, process};
+
+use crate::utils::dirs::Dirs;
+
+/// The apps that nginx answers with a maintenance page instead of passing
+/// requests to them. Kept outside of the app state so the mode survives
+/// `nbot run` and `nbot up`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Maintenance {
+    containers: Vec<String>,
+}
+
+impl Maintenance {
+    pub fn from_storage() -> Self {
+        let Ok(content) = fs::read_to_string(Dirs::maintenance_file()) else {
+            return Self::default();
+        };
+
+        match serde_json::from_str(&content) {
+            Ok(maintenance) => maintenance,
+            Err(e) => {
+                eprintln!("Maintenance file is invalid: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    /// True if the app is in maintenance mode.
+    pub fn is_on(container_name: &str) -> bool {
+        Self::from_storage()
+            .containers
+            .iter()
+            .any(|c| c == container_name)
+    }
+
+    /// Where the maintenance page of an app is found inside the nginx
+    /// container, relative to the html volume. Apps without a page of their
+    /// own get the default one.
+    pub fn page_path(container_name: &str) -> String {
+        format!("/maintenance/{}.html", container_name)
+    }
+
+    pub fn set(&mut self, container_name: &str, on: bool) {
+        self.containers.retain(|c| c != container_name);
+        if on {
+            self.containers.push(container_name.to_owned());
+        }
+        self.save();
+    }
+
+    fn save(&self) {
+        let content = serde_json::to_string_pretty(self).unwrap();
+        if let Err(e) = fs::write(Dirs::maintenance_file(), content) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
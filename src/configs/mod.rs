@@ -1 +1,5 @@
 pub mod app_state;
+pub mod history;
+pub mod htpasswd;
+pub mod maintenance;
+pub mod secrets;
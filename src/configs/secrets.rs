@@ -0,0 +1,203 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    process,
+};
+
+use crate::utils::dirs::Dirs;
+
+/// Where a secret ends up inside an app container.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SecretTarget {
+    /// Injected as an environment variable with the given name.
+    Env { secret: String, var: String },
+    /// Written to a read-only file at the given path before the container starts.
+    File { secret: String, path: String },
+}
+
+impl SecretTarget {
+    /// Parses an app's secret reference: `NAME` injects the secret as the env
+    /// var `NAME`, `NAME=VAR` as the env var `VAR` and `NAME:/path` as a file.
+    pub fn parse(spec: &str) -> Self {
+        if let Some((secret, path)) = spec.split_once(':') {
+            return SecretTarget::File {
+                secret: secret.to_owned(),
+                path: path.to_owned(),
+            };
+        }
+
+        match spec.split_once('=') {
+            Some((secret, var)) => SecretTarget::Env {
+                secret: secret.to_owned(),
+                var: var.to_owned(),
+            },
+            None => SecretTarget::Env {
+                secret: spec.to_owned(),
+                var: spec.to_owned(),
+            },
+        }
+    }
+
+    pub fn secret(&self) -> &str {
+        match self {
+            SecretTarget::Env { secret, .. } => secret,
+            SecretTarget::File { secret, .. } => secret,
+        }
+    }
+}
+
+/// Secret values, encrypted with a key that is generated on first use and
+/// kept next to the config file. Only references to secrets are ever stored
+/// in the app state.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Secrets {
+    projects: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Secrets {
+    pub fn from_storage() -> Self {
+        let Ok(content) = fs::read_to_string(Dirs::secrets_file()) else {
+            return Self::default();
+        };
+
+        match serde_json::from_str(&content) {
+            Ok(secrets) => secrets,
+            Err(e) => {
+                eprintln!("Secrets file is invalid: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let content = serde_json::to_string(&self).unwrap();
+        Self::write_private(&Dirs::secrets_file(), content.as_bytes());
+    }
+
+    pub fn set(&mut self, project: &str, name: &str, value: &str) {
+        let cipher = Self::cipher();
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let Ok(encrypted) = cipher.encrypt(&nonce, value.as_bytes()) else {
+            eprintln!("Error encrypting secret");
+            process::exit(1);
+        };
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(encrypted);
+
+        self.projects
+            .entry(project.to_owned())
+            .or_default()
+            .insert(name.to_owned(), STANDARD.encode(sealed));
+        self.save();
+    }
+
+    pub fn get(&self, project: &str, name: &str) -> Option<String> {
+        let sealed = self.projects.get(project)?.get(name)?;
+        let sealed = STANDARD.decode(sealed).ok()?;
+        if sealed.len() < 12 {
+            return None;
+        }
+
+        let (nonce, encrypted) = sealed.split_at(12);
+        let decrypted = Self::cipher()
+            .decrypt(Nonce::from_slice(nonce), encrypted)
+            .ok()?;
+        String::from_utf8(decrypted).ok()
+    }
+
+    pub fn remove(&mut self, project: &str, name: &str) -> bool {
+        let Some(secrets) = self.projects.get_mut(project) else {
+            return false;
+        };
+
+        let removed = secrets.remove(name).is_some();
+        if secrets.is_empty() {
+            self.projects.remove(project);
+        }
+        self.save();
+        removed
+    }
+
+    /// A digest of `data` keyed with the secrets key, so it can be shown
+    /// without revealing anything about the data. It is the authentication
+    /// tag of `data` under a random nonce, stored with the nonce in base64.
+    pub fn digest(data: &[u8]) -> String {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        Self::digest_with_nonce(data, &nonce)
+    }
+
+    /// True if `digest` was made by `digest` for the same data.
+    pub fn matches_digest(data: &[u8], digest: &str) -> bool {
+        let Ok(sealed) = STANDARD.decode(digest) else {
+            return false;
+        };
+        if sealed.len() < 12 {
+            return false;
+        }
+
+        let nonce = Nonce::from_slice(&sealed[..12]);
+        Self::digest_with_nonce(data, nonce) == digest
+    }
+
+    fn digest_with_nonce(data: &[u8], nonce: &Nonce) -> String {
+        // Sealing nothing with the data as associated data leaves only its tag
+        let payload = Payload {
+            msg: &[],
+            aad: data,
+        };
+        let Ok(tag) = Self::cipher().encrypt(nonce, payload) else {
+            eprintln!("Error computing digest");
+            process::exit(1);
+        };
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(tag);
+        STANDARD.encode(sealed)
+    }
+
+    fn cipher() -> ChaCha20Poly1305 {
+        let key_file = Dirs::secrets_key_file();
+        let key = match fs::read(&key_file) {
+            Ok(key) if key.len() == 32 => key,
+            Ok(_) => {
+                eprintln!("Secrets key {} is invalid", key_file);
+                process::exit(1);
+            }
+            Err(_) => {
+                let key = ChaCha20Poly1305::generate_key(&mut OsRng).to_vec();
+                Self::write_private(&key_file, &key);
+                key
+            }
+        };
+
+        ChaCha20Poly1305::new(Key::from_slice(&key))
+    }
+
+    /// Writes a file that only the owner can read, also when it already
+    /// existed with wider permissions.
+    fn write_private(path: &str, content: &[u8]) {
+        let written = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .and_then(|mut file| {
+                file.set_permissions(fs::Permissions::from_mode(0o600))?;
+                file.write_all(content)
+            });
+        if let Err(e) = written {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -1,28 +1,38 @@
 use crate::utils::{
     contants::{
-        NGINX_CERT_VOLUME, NGINX_CONFD_VOLUME, NGINX_CONTAINER_NAME, NGINX_HTML_VOLUME,
-        NGINX_IMAGE_NAME, NGINX_MEDIA_VOLUME, NGINX_STATIC_VOLUME,
+        NGINX_AUTH_VOLUME, NGINX_CERT_VOLUME, NGINX_CONFD_VOLUME, NGINX_CONTAINER_NAME,
+        NGINX_HTML_VOLUME, NGINX_IMAGE_NAME, NGINX_MEDIA_VOLUME, NGINX_STATIC_VOLUME,
+        NGINX_STREAMD_VOLUME, SECRET_FILES_LABEL,
     },
     networks::Network,
+    tarball::Tarball,
 };
 use bollard::{
     container::{
-        Config, CreateContainerOptions, ListContainersOptions, NetworkingConfig,
-        StartContainerOptions,
+        Config, CreateContainerOptions, ListContainersOptions, MemoryStatsStats, NetworkingConfig,
+        RenameContainerOptions, StartContainerOptions, StatsOptions, UploadToContainerOptions,
     },
     secret::{
-        ContainerCreateResponse, ContainerSummary, EndpointSettings, HostConfig, PortBinding,
+        ContainerCreateResponse, ContainerSummary, EndpointSettings, HealthConfig,
+        HealthStatusEnum, HostConfig, PortBinding, ResourcesUlimits, RestartPolicyNameEnum,
     },
 };
-use std::{collections::HashMap, default::Default};
+use futures_util::stream::StreamExt;
+use std::{collections::HashMap, default::Default, time::Duration};
 
-use crate::{models::App, APP_STATE, DOCKER};
+use crate::{
+    models::{App, RestartPolicy},
+    APP_STATE, DOCKER,
+};
 
 use super::images;
 
 pub async fn find_by_name(name: &str) -> Option<ContainerSummary> {
+    // The filter is a regular expression matching anywhere in the name, so
+    // `app` would also match its replicas `app_2` and `app_next`
+    let pattern = format!("^/{}$", name.replace('.', r"\."));
     let mut filters = HashMap::new();
-    filters.insert("name".to_owned(), vec![name.to_owned()]);
+    filters.insert("name".to_owned(), vec![pattern]);
 
     let options = Some(ListContainersOptions::<String> {
         all: true,
@@ -38,29 +48,127 @@ pub async fn find_by_name(name: &str) -> Option<ContainerSummary> {
     Some(containers[0].clone())
 }
 
+pub async fn find_env(name: &str) -> Option<Vec<String>> {
+    let details = DOCKER.inspect_container(name, None).await.ok()?;
+    details.config?.env
+}
+
+/// The environment the image of a container defines, which the container
+/// inherits.
+pub async fn find_image_env(name: &str) -> Option<Vec<String>> {
+    let details = DOCKER.inspect_container(name, None).await.ok()?;
+    let image = DOCKER.inspect_image(&details.image?).await.ok()?;
+    image.config?.env
+}
+
+pub async fn find_labels(name: &str) -> Option<HashMap<String, String>> {
+    let details = DOCKER.inspect_container(name, None).await.ok()?;
+    details.config?.labels
+}
+
+/// The published ports of a container as `PORT/PROTOCOL`, e.g. `80/tcp`.
+pub async fn find_published_ports(name: &str) -> Option<Vec<String>> {
+    let details = DOCKER.inspect_container(name, None).await.ok()?;
+    let bindings = details.host_config?.port_bindings.unwrap_or_default();
+    Some(bindings.into_keys().collect())
+}
+
+/// Host ports published by running containers other than `except`, as
+/// `PORT/PROTOCOL`.
+pub async fn published_host_ports(except: &str) -> Vec<String> {
+    let options = Some(ListContainersOptions::<String> {
+        all: false,
+        ..Default::default()
+    });
+    let Ok(containers) = DOCKER.list_containers(options).await else {
+        return vec![];
+    };
+
+    containers
+        .into_iter()
+        .filter(|c| {
+            let names = c.names.to_owned().unwrap_or_default();
+            !names.iter().any(|n| n.trim_start_matches('/') == except)
+        })
+        .flat_map(|c| c.ports.unwrap_or_default())
+        .filter_map(|port| {
+            let public_port = port.public_port?;
+            let protocol = port.typ.map(|t| t.to_string()).unwrap_or("tcp".to_owned());
+            Some(format!("{}/{}", public_port, protocol))
+        })
+        .collect()
+}
+
 pub async fn create_from_app(
     app: &App,
+    container_name: &str,
     networks: Option<&Vec<&Network>>,
 ) -> Result<ContainerCreateResponse, String> {
     let app = app.clone();
 
-    let image = match images::try_find_or_pull(&app.image, None).await {
-        Some(i) => i,
-        None => {
-            return Err(format!("Image not found: {}", app.image));
-        }
+    let image_id = match &app.image_id {
+        Some(image_id) => image_id.to_owned(),
+        None => match images::try_find_or_pull(&app.image, None).await {
+            Some(i) => i.id,
+            None => {
+                return Err(format!("Image not found: {}", app.image));
+            }
+        },
     };
 
     let options = Some(CreateContainerOptions {
-        name: &app.container_name,
+        name: container_name,
         platform: None,
     });
 
+    let mut port_bindings: HashMap<String, Option<Vec<PortBinding>>> = HashMap::new();
+    for mapping in &app.publish {
+        for (key, host_port) in mapping.bindings() {
+            let binding = PortBinding {
+                host_ip: mapping.host_ip.map(|ip| ip.to_string()),
+                host_port: Some(host_port.to_string()),
+            };
+            port_bindings
+                .entry(key)
+                .or_default()
+                .get_or_insert_with(Vec::new)
+                .push(binding);
+        }
+    }
+    let exposed_ports = port_bindings
+        .keys()
+        .map(|key| (key.to_owned(), HashMap::new()))
+        .collect();
+
     let host_config = Some(HostConfig {
-        // port_bindings,
+        port_bindings: Some(port_bindings),
         init: Some(true),
         privileged: Some(app.privileged),
         binds: Some(app.volumes.clone()),
+        restart_policy: app.restart.map(restart_policy),
+        memory: app.memory.map(|memory| memory.bytes as i64),
+        memory_reservation: app.memory_reservation.map(|memory| memory.bytes as i64),
+        nano_cpus: app.cpus.map(|cpus| (cpus * 1e9) as i64),
+        cpu_shares: app.cpu_shares.map(i64::from),
+        pids_limit: app.pids_limit.map(i64::from),
+        cap_add: Some(app.cap_add.clone()),
+        cap_drop: Some(app.cap_drop.clone()),
+        readonly_rootfs: Some(app.read_only),
+        tmpfs: Some(pairs(&app.tmpfs, ':')),
+        sysctls: Some(pairs(&app.sysctls, '=')),
+        ulimits: Some(
+            app.ulimits
+                .iter()
+                .map(|ulimit| ResourcesUlimits {
+                    name: Some(ulimit.name.to_owned()),
+                    soft: Some(ulimit.soft),
+                    hard: Some(ulimit.hard()),
+                })
+                .collect(),
+        ),
+        extra_hosts: Some(app.extra_hosts.clone()),
+        dns: Some(app.dns.clone()),
+        shm_size: app.shm_size.map(|size| size.bytes as i64),
         ..Default::default()
     });
 
@@ -79,27 +187,179 @@ pub async fn create_from_app(
         }
     }
 
+    let env = app.resolve_env()?;
+    let secret_files = app.resolve_secret_files()?;
+
     let networking_config = Some(NetworkingConfig { endpoints_config });
-    let cmd: Option<Vec<String>> = app
-        .cmd
-        .map(|cmd| cmd.split_whitespace().map(|s| s.to_string()).collect());
+
+    let mut labels = pairs(&app.labels, '=');
+    if let Some(digest) = App::secret_files_digest(&secret_files) {
+        labels.insert(SECRET_FILES_LABEL.to_owned(), digest);
+    }
+
+    let healthcheck = app.health_check.as_ref().map(|check| {
+        let port = app.port.as_deref().unwrap_or("80");
+        let nanos = |duration: std::time::Duration| Some(duration.as_nanos() as i64);
+        HealthConfig {
+            test: Some(vec!["CMD-SHELL".to_owned(), check.command(port)]),
+            interval: nanos(check.interval()),
+            timeout: nanos(check.timeout()),
+            retries: Some(check.retries() as i64),
+            start_period: nanos(check.start_period()),
+            // Check often while the app starts, so deploys are not held up
+            start_interval: check
+                .start_period
+                .as_ref()
+                .and(nanos(Duration::from_secs(1))),
+        }
+    });
 
     let config = Config {
-        image: Some(image.id),
-        env: Some(app.env_vars),
+        image: Some(image_id),
+        env: Some(env),
         host_config,
         networking_config,
-        cmd,
+        cmd: app.cmd.to_owned(),
+        entrypoint: app.entrypoint.to_owned(),
+        user: app.user.to_owned(),
+        working_dir: app.working_dir.to_owned(),
+        hostname: app.hostname.to_owned(),
+        labels: Some(labels),
+        healthcheck,
+        exposed_ports: Some(exposed_ports),
         ..Default::default()
     };
 
     let container = DOCKER.create_container(options, config).await;
-    match container {
-        Ok(container) => Ok(container),
-        Err(e) => Err(e.to_string()),
+    let container = match container {
+        Ok(container) => container,
+        Err(e) => return Err(e.to_string()),
+    };
+
+    // Secret files are copied into the container before it starts, so their
+    // values never show up in the container config
+    let files: Vec<(&str, &str)> = secret_files
+        .iter()
+        .map(|(path, value)| (path.trim_start_matches('/'), value.as_str()))
+        .collect();
+    if !files.is_empty() {
+        upload(&container.id, files, 0o444).await?;
+    }
+
+    Ok(container)
+}
+
+/// The health status of a container with a health check, such as `healthy`,
+/// and the output of its latest check.
+pub async fn health(name: &str) -> Option<(String, String)> {
+    let container = DOCKER.inspect_container(name, None).await.ok()?;
+    let health = container.state?.health?;
+    let status = match health.status? {
+        HealthStatusEnum::HEALTHY => "healthy",
+        HealthStatusEnum::UNHEALTHY => "unhealthy",
+        HealthStatusEnum::STARTING => "starting",
+        HealthStatusEnum::NONE | HealthStatusEnum::EMPTY => return None,
+    };
+    let output = health
+        .log
+        .and_then(|log| log.last().and_then(|result| result.output.to_owned()))
+        .unwrap_or_default();
+    Some((status.to_owned(), output.trim().to_owned()))
+}
+
+/// Resource usage of a running container, as `docker stats` reports it.
+pub struct Usage {
+    /// Memory in use, without the page cache the kernel can reclaim.
+    pub memory: u64,
+    /// CPU time used, in percent of one CPU.
+    pub cpu_percent: f64,
+    pub pids: u64,
+}
+
+/// Samples the resource usage of a container. Takes about a second, as
+/// Docker measures the CPU usage over two reads.
+pub async fn usage(name: &str) -> Option<Usage> {
+    let options = Some(StatsOptions {
+        stream: false,
+        one_shot: false,
+    });
+    let stats = DOCKER.stats(name, options).next().await?.ok()?;
+
+    let memory = &stats.memory_stats;
+    let reclaimable = match memory.stats {
+        Some(MemoryStatsStats::V1(stats)) => stats.total_inactive_file,
+        Some(MemoryStatsStats::V2(stats)) => stats.inactive_file,
+        None => 0,
+    };
+    let memory = memory.usage.unwrap_or(0).saturating_sub(reclaimable);
+
+    let cpu = &stats.cpu_stats;
+    let precpu = &stats.precpu_stats;
+    let cpu_delta = cpu
+        .cpu_usage
+        .total_usage
+        .saturating_sub(precpu.cpu_usage.total_usage);
+    let system_delta = cpu
+        .system_cpu_usage
+        .unwrap_or(0)
+        .saturating_sub(precpu.system_cpu_usage.unwrap_or(0));
+    let cpu_percent = match system_delta {
+        0 => 0.0,
+        _ => {
+            let online_cpus = cpu.online_cpus.unwrap_or(1) as f64;
+            cpu_delta as f64 / system_delta as f64 * online_cpus * 100.0
+        }
+    };
+
+    Some(Usage {
+        memory,
+        cpu_percent,
+        pids: stats.pids_stats.current.unwrap_or(0),
+    })
+}
+
+/// Splits `KEY=VALUE` style options into a map, values may be empty.
+fn pairs(options: &[String], separator: char) -> HashMap<String, String> {
+    options
+        .iter()
+        .map(|option| match option.split_once(separator) {
+            Some((key, value)) => (key.to_owned(), value.to_owned()),
+            None => (option.to_owned(), String::new()),
+        })
+        .collect()
+}
+
+fn restart_policy(policy: RestartPolicy) -> bollard::secret::RestartPolicy {
+    let (name, maximum_retry_count) = match policy {
+        RestartPolicy::No => (RestartPolicyNameEnum::NO, None),
+        RestartPolicy::OnFailure { max_retries } => (
+            RestartPolicyNameEnum::ON_FAILURE,
+            max_retries.map(i64::from),
+        ),
+        RestartPolicy::UnlessStopped => (RestartPolicyNameEnum::UNLESS_STOPPED, None),
+        RestartPolicy::Always => (RestartPolicyNameEnum::ALWAYS, None),
+    };
+    bollard::secret::RestartPolicy {
+        name: Some(name),
+        maximum_retry_count,
     }
 }
 
+/// Extracts the given files into the container, relative to its root.
+pub async fn upload(container_id: &str, files: Vec<(&str, &str)>, mode: u32) -> Result<(), String> {
+    let tarball = Tarball::create_with_mode(files, mode).map_err(|e| e.to_string())?;
+
+    let options = Some(UploadToContainerOptions {
+        path: "/",
+        ..Default::default()
+    });
+
+    DOCKER
+        .upload_to_container(container_id, options, tarball.into())
+        .await
+        .map_err(|e| e.to_string())
+}
+
 pub async fn start(container_id: &str) -> bool {
     let started = DOCKER
         .start_container(container_id, None::<StartContainerOptions<String>>)
@@ -129,6 +389,17 @@ pub async fn remove(container_id: &str) {
     }
 }
 
+pub async fn rename(container_id: &str, name: &str) -> bool {
+    let options = RenameContainerOptions { name };
+    match DOCKER.rename_container(container_id, options).await {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("Error renaming container {}: {}", container_id, e);
+            false
+        }
+    }
+}
+
 pub async fn start_nginx() -> bool {
     let image = super::images::find_by_name(NGINX_IMAGE_NAME, Some("latest")).await;
     let Some(image) = image else {
@@ -168,7 +439,9 @@ pub async fn start_nginx() -> bool {
     started
 }
 
-pub async fn run_nginx() -> bool {
+/// Creates and starts the nginx container. Besides 80 and 443, the host
+/// ports in `stream_ports` (as `PORT/PROTOCOL`) are published.
+pub async fn run_nginx(stream_ports: &[String]) -> bool {
     let image = super::images::find_by_name(NGINX_IMAGE_NAME, Some("latest")).await;
 
     let Some(image) = image else {
@@ -182,39 +455,47 @@ pub async fn run_nginx() -> bool {
         platform: None,
     });
 
-    let port_bindings = Some(HashMap::from([
-        (
-            "80/tcp".to_string(),
-            Some(vec![PortBinding {
-                host_ip: Some("0.0.0.0".to_string()),
-                host_port: Some("80".to_string()),
-            }]),
-        ),
-        (
-            "443/tcp".to_string(),
-            Some(vec![PortBinding {
+    let mut ports = vec!["80/tcp".to_owned(), "443/tcp".to_owned()];
+    ports.extend(stream_ports.iter().cloned());
+
+    let port_bindings = ports
+        .iter()
+        .map(|key| {
+            let host_port = key.split('/').next().unwrap_or_default().to_owned();
+            let binding = PortBinding {
                 host_ip: Some("0.0.0.0".to_string()),
-                host_port: Some("443".to_string()),
-            }]),
-        ),
-    ]));
+                host_port: Some(host_port),
+            };
+            (key.to_owned(), Some(vec![binding]))
+        })
+        .collect();
+
+    let exposed_ports = ports
+        .iter()
+        .map(|key| (key.as_str(), HashMap::new()))
+        .collect();
 
     let binds = Some(vec![
         NGINX_CERT_VOLUME.to_owned(),
         NGINX_CONFD_VOLUME.to_owned(),
+        NGINX_STREAMD_VOLUME.to_owned(),
+        NGINX_AUTH_VOLUME.to_owned(),
         NGINX_HTML_VOLUME.to_owned(),
         NGINX_STATIC_VOLUME.to_owned(),
         NGINX_MEDIA_VOLUME.to_owned(),
     ]);
 
+    // nginx comes back after a crash or reboot, unless it was stopped
     let host_config = Some(HostConfig {
-        port_bindings,
+        port_bindings: Some(port_bindings),
         binds,
+        restart_policy: Some(restart_policy(RestartPolicy::UnlessStopped)),
         ..Default::default()
     });
 
     let config = Config {
         image: Some(image.id.as_str()),
+        exposed_ports: Some(exposed_ports),
         host_config,
         ..Default::default()
     };
@@ -32,18 +32,16 @@ pub async fn find_by_name(image_name: &str, tag: Option<&str>) -> Option<ImageSu
     match &images.len() {
         1 => Some(images[0].clone()),
         _ => {
-            let Some(mut found) = images.first() else {
-                return None;
-            };
+            let mut found = images.first()?;
 
             for image in &images {
                 if image.repo_tags.contains(&format!("{}:latest", &image_name)) {
-                    found = &image;
+                    found = image;
                     break;
                 }
 
                 if image.created > found.created {
-                    found = &image;
+                    found = image;
                 }
             }
             Some(found.clone())
@@ -51,6 +49,11 @@ pub async fn find_by_name(image_name: &str, tag: Option<&str>) -> Option<ImageSu
     }
 }
 
+/// True if the image with the given ID is still available locally.
+pub async fn exists(image_id: &str) -> bool {
+    DOCKER.inspect_image(image_id).await.is_ok()
+}
+
 pub async fn remove(image_id: &str) -> bool {
     let result = DOCKER.remove_image(image_id, None, None).await;
     match result {
@@ -68,7 +71,6 @@ pub async fn build_nginx() {
         ("entrypoint.sh", f::NGINX_ENTRYPOINT),
         ("scheduler.txt", f::NGINX_SCHEDULER),
         ("default.conf", f::NGINX_DEFAULT_CONF),
-        ("template.conf", f::NGINX_TEMPLATE_CONF),
         ("functions.sh", f::NGINX_FUNCTIONS),
     ];
 
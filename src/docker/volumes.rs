@@ -1,6 +1,20 @@
-use bollard::volume::RemoveVolumeOptions;
+use bollard::{
+    container::{
+        Config, CreateContainerOptions, DownloadFromContainerOptions, ListContainersOptions,
+        RemoveContainerOptions, UploadToContainerOptions, WaitContainerOptions,
+    },
+    secret::HostConfig,
+    volume::{CreateVolumeOptions, RemoveVolumeOptions},
+};
+use futures_util::stream::StreamExt;
+use std::{collections::HashMap, fs::File, io::Write};
 
-use crate::DOCKER;
+use crate::{
+    utils::contants::{VOLUME_HELPER_IMAGE, VOLUME_HELPER_LABEL},
+    DOCKER,
+};
+
+use super::images;
 
 pub async fn find_by_container(container: &str) -> Vec<String> {
     let details = DOCKER.inspect_container(container, None).await;
@@ -29,3 +43,229 @@ pub async fn remove_many(volumes: Vec<String>, force: bool) {
         }
     }
 }
+
+pub async fn exists(volume: &str) -> bool {
+    DOCKER.inspect_volume(volume).await.is_ok()
+}
+
+pub async fn create(volume: &str) -> Result<(), String> {
+    let options = CreateVolumeOptions {
+        name: volume,
+        ..Default::default()
+    };
+    match DOCKER.create_volume(options).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// The disk usage of every volume in bytes, by name. Docker reports -1 for
+/// volumes it could not measure.
+pub async fn sizes() -> HashMap<String, i64> {
+    let Ok(usage) = DOCKER.df().await else {
+        return HashMap::new();
+    };
+    usage
+        .volumes
+        .unwrap_or_default()
+        .into_iter()
+        .map(|volume| {
+            let size = volume.usage_data.map(|usage| usage.size).unwrap_or(-1);
+            (volume.name, size)
+        })
+        .collect()
+}
+
+/// Names of the running containers that mount the volume.
+pub async fn used_by_running(volume: &str) -> Vec<String> {
+    let mut filters = HashMap::new();
+    filters.insert("volume".to_owned(), vec![volume.to_owned()]);
+    let options = Some(ListContainersOptions::<String> {
+        filters,
+        ..Default::default()
+    });
+
+    let containers = DOCKER.list_containers(options).await.unwrap_or_default();
+    containers
+        .into_iter()
+        .flat_map(|container| container.names.unwrap_or_default())
+        .map(|name| name.trim_start_matches('/').to_owned())
+        .collect()
+}
+
+/// Writes a tarball of the volume's contents to `output`, gzipped if `gzip`
+/// is set. A helper container packs the volume, its tarball is streamed out
+/// through the archive API.
+pub async fn backup(volume: &str, output: &mut File, gzip: bool) -> Result<(), String> {
+    let flags = if gzip { "-czf" } else { "-cf" };
+    let cmd = vec!["tar", flags, "/backup.tar", "-C", "/volume", "."];
+    let helper = Helper::run(volume, true, cmd).await?;
+
+    let options = Some(DownloadFromContainerOptions {
+        path: "/backup.tar",
+    });
+    let stream = DOCKER.download_from_container(&helper.id, options);
+    let written = write_first_file(stream, output).await;
+
+    helper.remove().await;
+    written
+}
+
+/// Replaces the contents of the volume with a tarball, which may be gzipped.
+/// Docker's archive API extracts it into the volume, mounted in a helper
+/// container.
+pub async fn restore(volume: &str, tarball: Vec<u8>) -> Result<(), String> {
+    let cmd = vec!["find", "/volume", "-mindepth", "1", "-delete"];
+    let helper = Helper::run(volume, false, cmd).await?;
+
+    let options = Some(UploadToContainerOptions {
+        path: "/volume",
+        ..Default::default()
+    });
+    let uploaded = DOCKER
+        .upload_to_container(&helper.id, options, tarball.into())
+        .await
+        .map_err(|e| e.to_string());
+
+    helper.remove().await;
+    uploaded
+}
+
+/// A short-lived container with the volume mounted at /volume.
+struct Helper {
+    id: String,
+}
+
+impl Helper {
+    /// Creates the helper, runs `cmd` in it and waits for it to exit.
+    async fn run(volume: &str, read_only: bool, cmd: Vec<&str>) -> Result<Self, String> {
+        let Some(image) = images::try_find_or_pull(VOLUME_HELPER_IMAGE, None).await else {
+            return Err(format!("Image not found: {}", VOLUME_HELPER_IMAGE));
+        };
+
+        // Helpers of a previous run that was interrupted. Docker names the
+        // helpers, so they never take the name of an app container.
+        let label = format!("{}={}", VOLUME_HELPER_LABEL, volume);
+        let mut filters = HashMap::new();
+        filters.insert("label".to_owned(), vec![label]);
+        let options = Some(ListContainersOptions::<String> {
+            all: true,
+            filters,
+            ..Default::default()
+        });
+        let leftovers = DOCKER.list_containers(options).await.unwrap_or_default();
+        for leftover in leftovers.into_iter().filter_map(|c| c.id) {
+            let remove_options = Some(RemoveContainerOptions {
+                force: true,
+                ..Default::default()
+            });
+            let _ = DOCKER.remove_container(&leftover, remove_options).await;
+        }
+
+        let mode = if read_only { "ro" } else { "rw" };
+        let config = Config {
+            image: Some(image.id),
+            cmd: Some(cmd.into_iter().map(String::from).collect()),
+            host_config: Some(HostConfig {
+                binds: Some(vec![format!("{}:/volume:{}", volume, mode)]),
+                ..Default::default()
+            }),
+            labels: Some(HashMap::from([(
+                VOLUME_HELPER_LABEL.to_owned(),
+                volume.to_owned(),
+            )])),
+            ..Default::default()
+        };
+        let container = DOCKER
+            .create_container(None::<CreateContainerOptions<String>>, config)
+            .await
+            .map_err(|e| e.to_string())?;
+        let helper = Self { id: container.id };
+
+        if let Err(e) = helper.wait().await {
+            helper.remove().await;
+            return Err(e);
+        }
+        Ok(helper)
+    }
+
+    async fn wait(&self) -> Result<(), String> {
+        DOCKER
+            .start_container::<String>(&self.id, None)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut stream = DOCKER.wait_container(&self.id, None::<WaitContainerOptions<String>>);
+        match stream.next().await {
+            Some(Ok(_)) | None => Ok(()),
+            Some(Err(e)) => Err(format!("Helper container failed: {}", e)),
+        }
+    }
+
+    async fn remove(&self) {
+        let options = Some(RemoveContainerOptions {
+            force: true,
+            ..Default::default()
+        });
+        let _ = DOCKER.remove_container(&self.id, options).await;
+    }
+}
+
+/// Writes the content of the first file of a tar stream to `output`, as the
+/// stream comes in. Other entries, such as PAX headers, are skipped.
+async fn write_first_file<S, B, E>(mut stream: S, output: &mut File) -> Result<(), String>
+where
+    S: futures_util::Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    const BLOCK: usize = 512;
+    let mut header = Vec::with_capacity(BLOCK);
+    // Bytes left of the current entry, including the padding to a full block
+    let mut remaining = 0;
+    let mut content = 0;
+    let mut writing = false;
+    let mut written = false;
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        let mut data = chunk.as_ref();
+        while !data.is_empty() {
+            if remaining > 0 {
+                let length = remaining.min(data.len());
+                if writing && content > 0 {
+                    let end = length.min(content);
+                    output.write_all(&data[..end]).map_err(|e| e.to_string())?;
+                    content -= end;
+                }
+                remaining -= length;
+                data = &data[length..];
+                continue;
+            }
+
+            let length = (BLOCK - header.len()).min(data.len());
+            header.extend_from_slice(&data[..length]);
+            data = &data[length..];
+            if header.len() < BLOCK {
+                continue;
+            }
+            if header.iter().all(|byte| *byte == 0) || written {
+                // End of the archive, or the file was written already
+                return Ok(());
+            }
+
+            let entry = tar::Header::from_byte_slice(&header);
+            let size = entry.entry_size().map_err(|e| e.to_string())? as usize;
+            writing = entry.entry_type().is_file();
+            written = writing;
+            content = size;
+            remaining = size.div_ceil(BLOCK) * BLOCK;
+            header.clear();
+        }
+    }
+
+    match written {
+        true => Ok(()),
+        false => Err("The helper container returned no tarball".to_owned()),
+    }
+}
@@ -1,37 +1,182 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+};
 
 use clap::ArgMatches;
 use serde::{Deserialize, Serialize};
 
-use crate::{docker, utils::networks::Network, APP_STATE};
+use super::{
+    balance::Balance, health::HealthCheck, limits::RateLimit, memory::Memory, proxy::ProxyOptions,
+    publish::PortMapping, redirect::Redirect, restart::RestartPolicy, route::Route, stream::Stream,
+    tls::TlsMode, ulimit::Ulimit,
+};
+use crate::{
+    configs::secrets::{SecretTarget, Secrets},
+    docker,
+    utils::{contants::SECRET_FILES_LABEL, env::Env, networks::Network, shell::Shell},
+    APP_STATE,
+};
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct App {
     pub name: String,
     pub image: String,
+    /// Image the app runs instead of the one `image` refers to, set when a
+    /// project is rolled back to the image an earlier revision ran.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_id: Option<String>,
     pub container_name: String,
     pub port: Option<String>,
     pub env_vars: Vec<String>,
+    #[serde(default)]
+    pub env_files: Vec<String>,
+    #[serde(default)]
+    pub secrets: Vec<String>,
     pub volumes: Vec<String>,
-    depends_on: Vec<String>,
+    pub depends_on: Vec<String>,
     pub domains: Option<Vec<String>>,
+    #[serde(default)]
+    pub redirects: Vec<Redirect>,
+    #[serde(default)]
+    pub routes: Vec<Route>,
+    #[serde(default)]
+    pub streams: Vec<Stream>,
+    /// Ports of the container published directly on the host.
+    #[serde(default)]
+    pub publish: Vec<PortMapping>,
+    #[serde(default)]
+    pub proxy: ProxyOptions,
+    /// Addresses or CIDR ranges that may access the app through nginx. Any
+    /// address may if empty.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Addresses or CIDR ranges that may not access the app through nginx.
+    #[serde(default)]
+    pub deny: Vec<String>,
+    #[serde(default)]
+    pub rate_limits: Vec<RateLimit>,
+    /// Maximum number of concurrent connections per client address.
+    #[serde(default)]
+    pub conn_limit: Option<u32>,
+    /// Number of containers running the app. One if unset.
+    #[serde(default)]
+    pub replicas: Option<u32>,
+    /// How nginx spreads requests over the replicas.
+    #[serde(default)]
+    pub balance: Option<Balance>,
+    #[serde(default)]
+    pub health_check: Option<HealthCheck>,
+    /// When Docker restarts the containers. Docker's default, no, if unset.
+    #[serde(default)]
+    pub restart: Option<RestartPolicy>,
+    /// Hard memory limit of each container.
+    #[serde(default)]
+    pub memory: Option<Memory>,
+    /// Memory each container gets when the host runs low.
+    #[serde(default)]
+    pub memory_reservation: Option<Memory>,
+    /// Number of CPUs each container may use, e.g. 0.5.
+    #[serde(default)]
+    pub cpus: Option<f64>,
+    /// Relative CPU weight of the containers when CPUs are busy. Docker's
+    /// default is 1024.
+    #[serde(default)]
+    pub cpu_shares: Option<u32>,
+    /// Maximum number of processes in each container.
+    #[serde(default)]
+    pub pids_limit: Option<u32>,
     pub email: Option<String>,
-    pub openssl: Option<bool>,
+    #[serde(
+        default,
+        alias = "openssl",
+        deserialize_with = "TlsMode::deserialize_legacy"
+    )]
+    pub tls: Option<TlsMode>,
+    #[serde(default)]
+    pub tls_cert: Option<String>,
+    #[serde(default)]
+    pub tls_key: Option<String>,
     pub privileged: bool,
     pub network_aliases: Vec<String>,
-    pub cmd: Option<String>,
+    /// Arguments of the command, replacing the cmd of the image.
+    #[serde(default, deserialize_with = "Shell::deserialize_args")]
+    pub cmd: Option<Vec<String>>,
+    /// User the processes run as, as USER[:GROUP].
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    /// Arguments of the entrypoint, replacing the one of the image.
+    #[serde(default, deserialize_with = "Shell::deserialize_args")]
+    pub entrypoint: Option<Vec<String>>,
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// Extra container labels, as KEY=VALUE.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub cap_add: Vec<String>,
+    #[serde(default)]
+    pub cap_drop: Vec<String>,
+    /// Mounts the root filesystem of the containers read-only.
+    #[serde(default)]
+    pub read_only: bool,
+    /// In-memory mounts, as PATH[:OPTIONS], e.g. /tmp:size=64m.
+    #[serde(default)]
+    pub tmpfs: Vec<String>,
+    /// Kernel parameters, as KEY=VALUE.
+    #[serde(default)]
+    pub sysctls: Vec<String>,
+    #[serde(default)]
+    pub ulimits: Vec<Ulimit>,
+    /// Extra entries for /etc/hosts, as HOST:IP.
+    #[serde(default)]
+    pub extra_hosts: Vec<String>,
+    /// DNS servers of the containers, instead of Docker's.
+    #[serde(default)]
+    pub dns: Vec<String>,
+    /// Size of /dev/shm. Docker's default is 64m.
+    #[serde(default)]
+    pub shm_size: Option<Memory>,
 }
 
 impl App {
+    /// Creates and starts every replica of the app. Replicas that already
+    /// run the latest image and environment are kept, unless `force` is set.
     pub async fn run(&self, networks: &Vec<&Network>, force: &bool) -> bool {
-        if !force && self.is_using_latest_image().await && self.is_running().await {
+        for container_name in self.container_names() {
+            if !self.run_replica(&container_name, networks, force).await {
+                return false;
+            }
+        }
+        true
+    }
+
+    async fn run_replica(
+        &self,
+        container_name: &str,
+        networks: &Vec<&Network>,
+        force: &bool,
+    ) -> bool {
+        if !force
+            && self.replica_uses_latest_image(container_name).await
+            && Self::replica_is_running(container_name).await
+            && !self.replica_has_outdated_env(container_name).await
+        {
             return true;
         }
 
-        self.stop().await;
-        self.remove().await;
+        docker::containers::stop(container_name).await;
+        docker::containers::remove(container_name).await;
+        self.run_container(container_name, networks).await
+    }
 
-        let result = docker::containers::create_from_app(self, Some(networks)).await;
+    /// Creates a container of the app under the given name, connects it to
+    /// the networks of the project and starts it.
+    pub async fn run_container(&self, container_name: &str, networks: &Vec<&Network>) -> bool {
+        let result =
+            docker::containers::create_from_app(self, container_name, Some(networks)).await;
         let container = match result {
             Ok(container) => container,
             Err(e) => {
@@ -43,7 +188,7 @@ impl App {
         for network in networks {
             let (connect, _) = match network {
                 Network::Internal(name) => (true, name),
-                Network::Nginx(name) => (self.domains.is_some(), name),
+                Network::Nginx(name) => (self.uses_nginx(), name),
             };
 
             if connect {
@@ -69,45 +214,80 @@ impl App {
     }
 
     pub async fn start(&self) -> bool {
-        let container = docker::containers::find_by_name(self.container_name.as_str()).await;
-        if let Some(container) = container {
-            if let Some(state) = container.state {
-                if state == "running" {
-                    return true;
+        for container_name in self.container_names() {
+            let container = docker::containers::find_by_name(&container_name).await;
+            if let Some(container) = container {
+                if container.state.as_deref() == Some("running") {
+                    continue;
                 }
-            }
-            let started = docker::containers::start(self.container_name.as_str()).await;
-            if !started {
-                eprintln!("Error starting container");
-                return false;
-            }
-        } else {
-            let container = docker::containers::create_from_app(self, None).await;
-            let Ok(_) = container else {
-                eprintln!("Error creating container");
-                return false;
-            };
+                let started = docker::containers::start(&container_name).await;
+                if !started {
+                    eprintln!("Error starting container");
+                    return false;
+                }
+            } else {
+                let container =
+                    docker::containers::create_from_app(self, &container_name, None).await;
+                let Ok(_) = container else {
+                    eprintln!("Error creating container");
+                    return false;
+                };
 
-            let started = docker::containers::start(self.container_name.as_str()).await;
-            if !started {
-                eprintln!("Error starting container");
-                return false;
-            }
-        };
+                let started = docker::containers::start(&container_name).await;
+                if !started {
+                    eprintln!("Error starting container");
+                    return false;
+                }
+            };
+        }
 
         true
     }
 
     pub async fn stop(&self) {
-        docker::containers::stop(self.container_name.as_str()).await;
+        for container_name in self.container_names() {
+            docker::containers::stop(&container_name).await;
+        }
     }
 
     pub async fn remove(&self) {
-        docker::containers::remove(self.container_name.as_str()).await;
+        for container_name in self.container_names() {
+            docker::containers::remove(&container_name).await;
+        }
+    }
+
+    /// Stops and removes the replicas that `previous` had and the app no
+    /// longer has, e.g. after scaling down.
+    pub async fn remove_surplus_replicas(&self, previous: &App) {
+        let container_names = self.container_names();
+        for container_name in previous.container_names() {
+            if !container_names.contains(&container_name) {
+                docker::containers::stop(&container_name).await;
+                docker::containers::remove(&container_name).await;
+            }
+        }
+    }
+
+    /// True if any replica has to be recreated to run the latest image and
+    /// environment, or is not running.
+    pub async fn needs_redeploy(&self) -> bool {
+        !(self.is_using_latest_image().await
+            && self.is_running().await
+            && !self.has_outdated_env().await)
     }
 
+    /// True if every replica is running.
     pub async fn is_running(&self) -> bool {
-        let container = docker::containers::find_by_name(self.container_name.as_str()).await;
+        for container_name in self.container_names() {
+            if !Self::replica_is_running(&container_name).await {
+                return false;
+            }
+        }
+        true
+    }
+
+    async fn replica_is_running(container_name: &str) -> bool {
+        let container = docker::containers::find_by_name(container_name).await;
         if let Some(container) = container {
             if let Some(state) = container.state {
                 return state == "running";
@@ -116,8 +296,18 @@ impl App {
         false
     }
 
-    async fn is_using_latest_image(&self) -> bool {
-        let container = docker::containers::find_by_name(self.container_name.as_str()).await;
+    /// True if every replica runs the latest image.
+    pub async fn is_using_latest_image(&self) -> bool {
+        for container_name in self.container_names() {
+            if !self.replica_uses_latest_image(&container_name).await {
+                return false;
+            }
+        }
+        true
+    }
+
+    async fn replica_uses_latest_image(&self, container_name: &str) -> bool {
+        let container = docker::containers::find_by_name(container_name).await;
         let Some(container) = container else {
             return false;
         };
@@ -125,6 +315,9 @@ impl App {
         let Some(image_id) = container.image_id else {
             return false;
         };
+        if let Some(pinned) = &self.image_id {
+            return &image_id == pinned;
+        }
 
         let image = docker::images::find_by_name(&self.image, None).await;
 
@@ -134,10 +327,209 @@ impl App {
         false
     }
 
+    /// Temporary name of the container that replaces `container_name` during
+    /// a redeploy.
+    pub fn next_name(container_name: &str) -> String {
+        format!("{}_next", container_name)
+    }
+
+    /// Number of containers running the app.
+    pub fn replica_count(&self) -> u32 {
+        self.replicas.unwrap_or(1)
+    }
+
+    /// Names of the containers of the app. The first replica keeps the
+    /// container name of the app, the others are numbered from 2.
+    pub fn container_names(&self) -> Vec<String> {
+        (1..=self.replica_count())
+            .map(|replica| match replica {
+                1 => self.container_name.to_owned(),
+                _ => format!("{}_{}", self.container_name, replica),
+            })
+            .collect()
+    }
+
+    /// Resolves the app's environment from its env files, host pass-through
+    /// variables and `${VAR}` references. Called on every deploy, so edits to
+    /// env files or host variables are picked up.
+    pub fn resolve_env(&self) -> Result<Vec<String>, String> {
+        let mut env = Env::resolve(&self.env_files, &self.env_vars)?;
+        for (target, value) in self.resolve_secrets()? {
+            if let SecretTarget::Env { var, .. } = target {
+                env.retain(|e| e.split_once('=').map(|(k, _)| k) != Some(var.as_str()));
+                env.push(format!("{var}={value}"));
+            }
+        }
+        Ok(env)
+    }
+
+    /// Paths and values of the secrets that are mounted as files.
+    pub fn resolve_secret_files(&self) -> Result<Vec<(String, String)>, String> {
+        let mut files = vec![];
+        for (target, value) in self.resolve_secrets()? {
+            if let SecretTarget::File { path, .. } = target {
+                files.push((path, value));
+            }
+        }
+        Ok(files)
+    }
+
+    /// Keyed digest of the secret files, stored as a label on the containers,
+    /// so changed files are noticed like a changed environment. `None`
+    /// without secret files.
+    pub fn secret_files_digest(files: &[(String, String)]) -> Option<String> {
+        (!files.is_empty()).then(|| Secrets::digest(&Self::secret_files_data(files)))
+    }
+
+    /// True if `files` are the secret files a container with the label
+    /// `digest` was created with.
+    fn secret_files_match(files: &[(String, String)], digest: Option<&str>) -> bool {
+        match digest {
+            Some(digest) => Secrets::matches_digest(&Self::secret_files_data(files), digest),
+            None => files.is_empty(),
+        }
+    }
+
+    fn secret_files_data(files: &[(String, String)]) -> Vec<u8> {
+        let mut data = vec![];
+        for (path, value) in files {
+            data.extend(path.bytes().chain([0]).chain(value.bytes()).chain([0]));
+        }
+        data
+    }
+
+    fn resolve_secrets(&self) -> Result<Vec<(SecretTarget, String)>, String> {
+        if self.secrets.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let store = Secrets::from_storage();
+        let project = self.project_name();
+        let mut resolved = vec![];
+        for spec in &self.secrets {
+            let target = SecretTarget::parse(spec);
+            let Some(value) = store.get(&project, target.secret()) else {
+                return Err(format!(
+                    "Secret {} not found for project {}. Add it with \"nbot secret set {} {}\"",
+                    target.secret(),
+                    project,
+                    project,
+                    target.secret()
+                ));
+            };
+            resolved.push((target, value));
+        }
+        Ok(resolved)
+    }
+
+    /// Name of the project the app belongs to, derived from the container
+    /// name `{prefix}{project}_{app}`.
+    pub fn project_name(&self) -> String {
+        let name = self
+            .container_name
+            .strip_prefix(&APP_STATE.container_prefix)
+            .unwrap_or(&self.container_name);
+        name.strip_suffix(&format!("_{}", self.name))
+            .unwrap_or(name)
+            .to_owned()
+    }
+
+    /// True if the environment of any replica differs from the currently
+    /// resolved environment of the app, or its secret files have changed.
+    pub async fn has_outdated_env(&self) -> bool {
+        for container_name in self.container_names() {
+            if self.replica_has_outdated_env(&container_name).await {
+                return true;
+            }
+        }
+        false
+    }
+
+    async fn replica_has_outdated_env(&self, container_name: &str) -> bool {
+        let (Ok(env), Ok(secret_files)) = (self.resolve_env(), self.resolve_secret_files()) else {
+            return true;
+        };
+        let Some(container_env) = docker::containers::find_env(container_name).await else {
+            return true;
+        };
+        let labels = docker::containers::find_labels(container_name).await;
+        let digest = labels.and_then(|mut labels| labels.remove(SECRET_FILES_LABEL));
+
+        // Variables the container has apart from those of its image were set
+        // by the app, so one it no longer sets has been removed. Without the
+        // image env every variable would look removed, so none is counted.
+        let key = |var: &str| var.split_once('=').map_or(var, |(key, _)| key).to_owned();
+        let keys: HashSet<String> = env.iter().map(|var| key(var)).collect();
+        let removed = match docker::containers::find_image_env(container_name).await {
+            Some(image_env) => container_env
+                .iter()
+                .filter(|var| !image_env.contains(var))
+                .any(|var| !keys.contains(&key(var))),
+            None => false,
+        };
+
+        env.iter().any(|var| !container_env.contains(var))
+            || removed
+            || !Self::secret_files_match(&secret_files, digest.as_deref())
+    }
+
+    /// True if the app is reachable through nginx.
+    pub fn is_public(&self) -> bool {
+        self.domains.is_some() || !self.routes.is_empty() || !self.redirects.is_empty()
+    }
+
+    /// True if nginx forwards any traffic to the app, HTTP or streams.
+    pub fn uses_nginx(&self) -> bool {
+        self.is_public() || !self.streams.is_empty()
+    }
+
+    /// Every domain the app serves, either entirely, through routes or as a
+    /// redirect.
+    pub fn served_domains(&self) -> Vec<String> {
+        let mut domains: Vec<String> = self.domains.clone().unwrap_or_default();
+        let others = self.routes.iter().map(|r| &r.domain);
+        for domain in others.chain(self.redirects.iter().map(|r| &r.from)) {
+            if !domains.contains(domain) {
+                domains.push(domain.to_owned());
+            }
+        }
+        domains
+    }
+
+    pub fn redirect_for(&self, domain: &str) -> Option<&Redirect> {
+        self.redirects.iter().find(|r| r.from == domain)
+    }
+
+    /// The routes the app serves on `domain`. An app serves the whole of each
+    /// of its domains, unless it declares explicit routes for that domain.
+    pub fn routes_for(&self, domain: &str) -> Vec<Route> {
+        let routes: Vec<Route> = self
+            .routes
+            .iter()
+            .filter(|r| r.domain == domain)
+            .cloned()
+            .collect();
+        if !routes.is_empty() {
+            return routes;
+        }
+
+        match &self.domains {
+            Some(domains) if domains.iter().any(|d| d == domain) => vec![Route {
+                domain: domain.to_owned(),
+                path: "/".to_owned(),
+                port: None,
+                strip_prefix: false,
+            }],
+            _ => vec![],
+        }
+    }
+
     pub fn from_cli(args: &ArgMatches, project: &String) -> Vec<Self> {
         let mut apps = Self::collect_flags::<String>(args, "app");
         let mut image_list = Self::collect_flags::<String>(args, "image");
         let mut env_list = Self::collect_flags::<String>(args, "env");
+        let mut env_file_list = Self::collect_flags::<String>(args, "env-file");
+        let mut secret_list = Self::collect_flags::<String>(args, "secret");
         let mut port_list = Self::collect_flags::<String>(args, "port");
         let mut volume_list = Self::collect_flags::<String>(args, "volume");
         let mut depends_on_list = Self::collect_flags::<String>(args, "depends-on");
@@ -146,6 +538,41 @@ impl App {
         let mut privileged_list = Self::collect_flags::<bool>(args, "privileged");
         let mut network_aliases_list = Self::collect_flags::<String>(args, "network-alias");
         let mut cmd_list = Self::collect_flags::<String>(args, "cmd");
+        let mut user_list = Self::collect_flags::<String>(args, "user");
+        let mut workdir_list = Self::collect_flags::<String>(args, "workdir");
+        let mut entrypoint_list = Self::collect_flags::<String>(args, "entrypoint");
+        let mut hostname_list = Self::collect_flags::<String>(args, "hostname");
+        let mut label_list = Self::collect_flags::<String>(args, "label");
+        let mut cap_add_list = Self::collect_flags::<String>(args, "cap-add");
+        let mut cap_drop_list = Self::collect_flags::<String>(args, "cap-drop");
+        let mut read_only_list = Self::collect_flags::<bool>(args, "read-only");
+        let mut tmpfs_list = Self::collect_flags::<String>(args, "tmpfs");
+        let mut sysctl_list = Self::collect_flags::<String>(args, "sysctl");
+        let mut ulimit_list = Self::collect_flags::<String>(args, "ulimit");
+        let mut add_host_list = Self::collect_flags::<String>(args, "add-host");
+        let mut dns_list = Self::collect_flags::<String>(args, "dns");
+        let mut shm_size_list = Self::collect_flags::<String>(args, "shm-size");
+
+        let mut route_list = Self::collect_flags::<String>(args, "route");
+        let mut stream_list = Self::collect_flags::<String>(args, "stream");
+        let mut publish_list = Self::collect_flags::<String>(args, "publish");
+        let mut proxy_list = Self::collect_flags::<String>(args, "proxy");
+        let mut allow_list = Self::collect_flags::<String>(args, "allow");
+        let mut deny_list = Self::collect_flags::<String>(args, "deny");
+        let mut rate_limit_list = Self::collect_flags::<String>(args, "rate-limit");
+        let mut conn_limit_list = Self::collect_flags::<u32>(args, "conn-limit");
+        let mut replicas_list = Self::collect_flags::<u32>(args, "replicas");
+        let mut balance_list = Self::collect_flags::<String>(args, "balance");
+        let mut health_list = Self::collect_flags::<String>(args, "health");
+        let mut restart_list = Self::collect_flags::<String>(args, "restart");
+        let mut memory_list = Self::collect_flags::<String>(args, "memory");
+        let mut memory_reservation_list = Self::collect_flags::<String>(args, "memory-reservation");
+        let mut cpus_list = Self::collect_flags::<f64>(args, "cpus");
+        let mut cpu_shares_list = Self::collect_flags::<u32>(args, "cpu-shares");
+        let mut pids_limit_list = Self::collect_flags::<u32>(args, "pids-limit");
+        let mut tls_list = Self::collect_flags::<String>(args, "tls");
+        let mut tls_cert_list = Self::collect_flags::<String>(args, "tls-cert");
+        let mut tls_key_list = Self::collect_flags::<String>(args, "tls-key");
 
         let uses_openssl = args.get_flag("openssl");
 
@@ -159,7 +586,6 @@ impl App {
             let mut domains: Vec<String> = vec![];
             let mut network_aliases: Vec<String> = vec![];
             let mut privileged = false;
-            let mut openssl: Option<bool> = None;
 
             while let Some(image_name) = image_list.pop() {
                 if image_name.index > app.index {
@@ -188,6 +614,17 @@ impl App {
                 }
             }
 
+            let env_files: Vec<String> = Self::take_values(&mut env_file_list, app.index)
+                .iter()
+                .map(|path| Self::absolute_path(path))
+                .collect();
+
+            let secrets = Self::take_values(&mut secret_list, app.index);
+
+            // Flags are collected back to front, but env entries override
+            // and reference earlier ones, so their order matters
+            env_vars.reverse();
+
             while let Some(port) = port_list.pop() {
                 if port.index > app.index {
                     if virtual_port.is_some() {
@@ -219,10 +656,18 @@ impl App {
                 }
             }
 
+            let mut redirects: Vec<Redirect> = vec![];
             while let Some(domain) = domain_list.pop() {
-                if domain.index > app.index {
+                if domain.index > app.index && Redirect::is_redirect(&domain.value) {
+                    match Redirect::parse(&domain.value) {
+                        Ok(redirect) => redirects.insert(0, redirect),
+                        Err(e) => {
+                            eprintln!("Error: {}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                } else if domain.index > app.index {
                     domains.push(domain.value);
-                    openssl = Some(uses_openssl);
                 } else {
                     domain_list.push(domain);
                     break;
@@ -271,43 +716,220 @@ impl App {
                 }
             }
 
-            let mut cmd: Option<String> = None;
+            let split_args = |line: String| {
+                Shell::split(&line).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                })
+            };
+
+            let mut cmd: Option<Vec<String>> = None;
             while let Some(cmd_flag) = cmd_list.pop() {
                 if cmd_flag.index > app.index {
                     if cmd.is_some() {
                         eprintln!("Error: App cannot have more than one cmd");
                         std::process::exit(1);
                     }
-                    cmd = Some(cmd_flag.value);
+                    cmd = Some(split_args(cmd_flag.value));
                 } else {
                     cmd_list.push(cmd_flag);
                     break;
                 }
             }
 
-            if virtual_port.is_none() && domains.is_some() {
-                virtual_port = Some("80".to_owned());
+            let mut routes = vec![];
+            for route in Self::take_values(&mut route_list, app.index) {
+                match Route::parse(&route) {
+                    Ok(route) => routes.push(route),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
             }
+            let is_public = domains.is_some() || !routes.is_empty() || !redirects.is_empty();
 
-            if email.is_none() && domains.is_some() {
-                eprintln!("Error: App must have an email if it has domains. This is required for SSL certificates.");
-                std::process::exit(1);
+            let allow = Self::take_values(&mut allow_list, app.index);
+            let deny = Self::take_values(&mut deny_list, app.index);
+
+            let mut rate_limits = vec![];
+            for limit in Self::take_values(&mut rate_limit_list, app.index) {
+                match RateLimit::parse(&limit) {
+                    Ok(limit) => rate_limits.push(limit),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
             }
+            let conn_limit = Self::take_value(&mut conn_limit_list, app.index, "conn-limit");
+
+            let replicas = Self::take_value(&mut replicas_list, app.index, "replicas");
+            let balance = Self::take_value(&mut balance_list, app.index, "balance").map(|name| {
+                Balance::from_name(&name).expect("balance method is validated by clap")
+            });
+
+            let health_check =
+                Self::take_value(&mut health_list, app.index, "health").map(|spec| {
+                    HealthCheck::parse(&spec).unwrap_or_else(|e| {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    })
+                });
+
+            let restart = Self::take_value(&mut restart_list, app.index, "restart").map(|policy| {
+                RestartPolicy::parse(&policy).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                })
+            });
+
+            let parse_memory = |memory: String| {
+                Memory::parse(&memory).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                })
+            };
+            let memory = Self::take_value(&mut memory_list, app.index, "memory").map(parse_memory);
+            let memory_reservation = Self::take_value(
+                &mut memory_reservation_list,
+                app.index,
+                "memory-reservation",
+            )
+            .map(parse_memory);
+            let cpus = Self::take_value(&mut cpus_list, app.index, "cpus");
+            let cpu_shares = Self::take_value(&mut cpu_shares_list, app.index, "cpu-shares");
+            let pids_limit = Self::take_value(&mut pids_limit_list, app.index, "pids-limit");
+
+            let user = Self::take_value(&mut user_list, app.index, "user");
+            let working_dir = Self::take_value(&mut workdir_list, app.index, "workdir");
+            let entrypoint =
+                Self::take_value(&mut entrypoint_list, app.index, "entrypoint").map(split_args);
+            let hostname = Self::take_value(&mut hostname_list, app.index, "hostname");
+            let labels = Self::take_values(&mut label_list, app.index);
+            let cap_add = Self::take_values(&mut cap_add_list, app.index);
+            let cap_drop = Self::take_values(&mut cap_drop_list, app.index);
+            let read_only = Self::take_value(&mut read_only_list, app.index, "read-only");
+            let tmpfs = Self::take_values(&mut tmpfs_list, app.index);
+            let sysctls = Self::take_values(&mut sysctl_list, app.index);
+            let extra_hosts = Self::take_values(&mut add_host_list, app.index);
+            let dns = Self::take_values(&mut dns_list, app.index);
+            let shm_size =
+                Self::take_value(&mut shm_size_list, app.index, "shm-size").map(parse_memory);
+
+            let mut ulimits = vec![];
+            for ulimit in Self::take_values(&mut ulimit_list, app.index) {
+                match Ulimit::parse(&ulimit) {
+                    Ok(ulimit) => ulimits.push(ulimit),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            let mut proxy = ProxyOptions::default();
+            for option in Self::take_values(&mut proxy_list, app.index) {
+                if let Err(e) = proxy.set(&option) {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+
+            let mut streams = vec![];
+            for stream in Self::take_values(&mut stream_list, app.index) {
+                match Stream::parse(&stream) {
+                    Ok(stream) => streams.push(stream),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            let mut publish = vec![];
+            for mapping in Self::take_values(&mut publish_list, app.index) {
+                match PortMapping::parse(&mapping) {
+                    Ok(mapping) => publish.push(mapping),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            let tls = Self::take_value(&mut tls_list, app.index, "tls")
+                .map(|mode| TlsMode::from_name(&mode).expect("tls mode is validated by clap"));
+            let tls_cert = Self::take_value(&mut tls_cert_list, app.index, "tls-cert")
+                .map(|path| Self::absolute_path(&path));
+            let tls_key = Self::take_value(&mut tls_key_list, app.index, "tls-key")
+                .map(|path| Self::absolute_path(&path));
+
+            // --openssl is the default for apps that do not set --tls
+            let default_tls = if uses_openssl {
+                TlsMode::Openssl
+            } else {
+                TlsMode::Letsencrypt
+            };
+            let tls = is_public.then(|| tls.unwrap_or(default_tls));
+
+            if virtual_port.is_none() && is_public {
+                virtual_port = Some("80".to_owned());
+            }
+
+            Self::validate_tls(&tls, &email, &tls_cert, &tls_key);
 
             app_list.push(App {
                 name: app.value.to_owned(),
                 image,
+                image_id: None,
                 container_name: format!("{}{}_{}", APP_STATE.container_prefix, project, app.value),
                 env_vars,
+                env_files,
+                secrets,
                 port: virtual_port,
                 volumes,
                 depends_on,
                 domains,
+                redirects,
+                routes,
+                streams,
+                publish,
+                proxy,
+                allow,
+                deny,
+                rate_limits,
+                conn_limit,
+                replicas,
+                balance,
+                health_check,
+                restart,
+                memory,
+                memory_reservation,
+                cpus,
+                cpu_shares,
+                pids_limit,
                 email,
-                openssl,
+                tls,
+                tls_cert,
+                tls_key,
                 privileged,
                 network_aliases,
                 cmd,
+                user,
+                working_dir,
+                entrypoint,
+                hostname,
+                labels,
+                cap_add,
+                cap_drop,
+                read_only: read_only.unwrap_or(false),
+                tmpfs,
+                sysctls,
+                ulimits,
+                extra_hosts,
+                dns,
+                shm_size,
             });
         }
 
@@ -317,6 +939,55 @@ impl App {
             error = Some("Error: Invalid image outside of app definition");
         } else if !env_list.is_empty() {
             error = Some("Error: Invalid environment variable outside of app definition");
+        } else if !env_file_list.is_empty() {
+            error = Some("Error: Invalid env file outside of app definition");
+        } else if !secret_list.is_empty() {
+            error = Some("Error: Invalid secret outside of app definition");
+        } else if !route_list.is_empty() {
+            error = Some("Error: Invalid route outside of app definition");
+        } else if !stream_list.is_empty() {
+            error = Some("Error: Invalid stream outside of app definition");
+        } else if !publish_list.is_empty() {
+            error = Some("Error: Invalid published port outside of app definition");
+        } else if !proxy_list.is_empty() {
+            error = Some("Error: Invalid proxy option outside of app definition");
+        } else if !allow_list.is_empty() || !deny_list.is_empty() {
+            error = Some("Error: Invalid allow or deny outside of app definition");
+        } else if !rate_limit_list.is_empty() || !conn_limit_list.is_empty() {
+            error = Some("Error: Invalid limit outside of app definition");
+        } else if !replicas_list.is_empty() || !balance_list.is_empty() {
+            error = Some("Error: Invalid replicas or balance outside of app definition");
+        } else if !health_list.is_empty() {
+            error = Some("Error: Invalid health check outside of app definition");
+        } else if !restart_list.is_empty() {
+            error = Some("Error: Invalid restart policy outside of app definition");
+        } else if !memory_list.is_empty()
+            || !memory_reservation_list.is_empty()
+            || !cpus_list.is_empty()
+            || !cpu_shares_list.is_empty()
+            || !pids_limit_list.is_empty()
+        {
+            error = Some("Error: Invalid resource limit outside of app definition");
+        } else if !user_list.is_empty()
+            || !workdir_list.is_empty()
+            || !entrypoint_list.is_empty()
+            || !hostname_list.is_empty()
+            || !label_list.is_empty()
+            || !cap_add_list.is_empty()
+            || !cap_drop_list.is_empty()
+            || !read_only_list.is_empty()
+            || !tmpfs_list.is_empty()
+            || !sysctl_list.is_empty()
+            || !ulimit_list.is_empty()
+            || !add_host_list.is_empty()
+            || !dns_list.is_empty()
+            || !shm_size_list.is_empty()
+        {
+            error = Some("Error: Invalid runtime option outside of app definition");
+        } else if !tls_list.is_empty() {
+            error = Some("Error: Invalid tls mode outside of app definition");
+        } else if !tls_cert_list.is_empty() || !tls_key_list.is_empty() {
+            error = Some("Error: Invalid tls certificate outside of app definition");
         } else if !port_list.is_empty() {
             error = Some("Error: Invalid port outside of app definition");
         } else if !volume_list.is_empty() {
@@ -334,10 +1005,39 @@ impl App {
             std::process::exit(1);
         }
 
-        for app in &app_list {
+        Self::validate(&app_list);
+
+        app_list.reverse();
+        app_list
+    }
+
+    /// Exits the process if the TLS settings of an app are incomplete.
+    pub fn validate_tls(
+        tls: &Option<TlsMode>,
+        email: &Option<String>,
+        tls_cert: &Option<String>,
+        tls_key: &Option<String>,
+    ) {
+        match tls {
+            Some(TlsMode::Letsencrypt) if email.is_none() => {
+                eprintln!("Error: App must have an email if it uses Let's Encrypt. This is required for SSL certificates.");
+                std::process::exit(1);
+            }
+            Some(TlsMode::Custom) if tls_cert.is_none() || tls_key.is_none() => {
+                eprintln!("Error: App must have --tls-cert and --tls-key if it uses custom TLS.");
+                std::process::exit(1);
+            }
+            _ => {}
+        }
+    }
+
+    /// Validates a complete list of apps belonging to one project. Exits the
+    /// process with an error message if the apps are inconsistent.
+    pub fn validate(apps: &[App]) {
+        for app in apps {
             // ensure app name is unique
             let mut count = 0;
-            for other_app in &app_list {
+            for other_app in apps {
                 if app.name == other_app.name {
                     count += 1;
                 }
@@ -354,7 +1054,7 @@ impl App {
                     std::process::exit(1);
                 }
                 let mut found = false;
-                for other_app in &app_list {
+                for other_app in apps {
                     if dependency == &other_app.name {
                         found = true;
                         break;
@@ -366,8 +1066,115 @@ impl App {
                 }
             }
 
+            // ensure replicas do not take the container name of another app
+            if app.replicas == Some(0) {
+                eprintln!("Error: App must have at least one replica");
+                std::process::exit(1);
+            }
+            if app.replica_count() > 1 && !app.publish.is_empty() {
+                eprintln!(
+                    "Error: Replicas of {} cannot share its published ports, use --stream instead",
+                    app.name
+                );
+                std::process::exit(1);
+            }
+            let container_names = app.container_names();
+            for other_app in apps {
+                if other_app.name != app.name && container_names.contains(&other_app.container_name)
+                {
+                    eprintln!(
+                        "Error: Replicas of {} would take the container name of {}",
+                        app.name, other_app.name
+                    );
+                    std::process::exit(1);
+                }
+            }
+
+            // ensure resource limits are values Docker accepts
+            if let Err(e) = app.validate_resources() {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+
+            if let Err(e) = app.validate_runtime() {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+
+            // ensure domains are plain host names, as they end up in file names
+            for domain in app.served_domains() {
+                let name = domain.strip_prefix("*.").unwrap_or(&domain);
+                let valid = !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+                if !valid {
+                    eprintln!("Error: Invalid domain {}", domain);
+                    std::process::exit(1);
+                }
+            }
+
+            // ensure access rules are addresses or CIDR ranges
+            for address in app.allow.iter().chain(&app.deny) {
+                if !Self::is_cidr(address) {
+                    eprintln!("Error: Invalid address or CIDR range {}", address);
+                    std::process::exit(1);
+                }
+            }
+
+            for limit in &app.rate_limits {
+                if let Err(e) = limit.validate() {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+
+            // ensure redirect targets are host names, optionally with a port
+            for redirect in &app.redirects {
+                let valid = redirect
+                    .to
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-.:[]".contains(c));
+                if !valid {
+                    eprintln!("Error: Invalid redirect target {}", redirect.to);
+                    std::process::exit(1);
+                }
+            }
+
+            // ensure a redirecting domain is neither proxied nor redirected twice
+            for redirect in &app.redirects {
+                let taken = apps.iter().any(|other_app| {
+                    !other_app.routes_for(&redirect.from).is_empty()
+                        || other_app
+                            .redirects
+                            .iter()
+                            .any(|r| r.from == redirect.from && !std::ptr::eq(r, redirect))
+                });
+                if taken {
+                    eprintln!("Error: {} is both redirected and served", redirect.from);
+                    std::process::exit(1);
+                }
+            }
+
+            // ensure no two apps serve the same path of a domain
+            for domain in app.served_domains() {
+                for route in app.routes_for(&domain) {
+                    let taken = apps.iter().any(|other_app| {
+                        other_app.name != app.name
+                            && other_app
+                                .routes_for(&domain)
+                                .iter()
+                                .any(|r| r.path == route.path)
+                    });
+                    if taken {
+                        eprintln!("Error: More than one app serves {}{}", domain, route.path);
+                        std::process::exit(1);
+                    }
+                }
+            }
+
             // ensure two apps don't depend on each other
-            for other_app in &app_list {
+            for other_app in apps {
                 if app.name == other_app.name {
                     continue;
                 }
@@ -379,9 +1186,85 @@ impl App {
                 }
             }
         }
+    }
 
-        app_list.reverse();
-        app_list
+    fn validate_resources(&self) -> Result<(), String> {
+        // Docker refuses containers with less memory
+        let minimum = Memory::parse("6m").unwrap();
+        for memory in [self.memory, self.memory_reservation].into_iter().flatten() {
+            if memory.bytes < minimum.bytes {
+                return Err(format!(
+                    "Memory of {} must be at least {}",
+                    self.name, minimum
+                ));
+            }
+        }
+        if let (Some(memory), Some(reservation)) = (self.memory, self.memory_reservation) {
+            if reservation.bytes > memory.bytes {
+                return Err(format!(
+                    "Memory reservation of {} must not exceed its memory limit",
+                    self.name
+                ));
+            }
+        }
+        if self
+            .cpus
+            .is_some_and(|cpus| !(cpus > 0.0 && cpus.is_finite()))
+        {
+            return Err(format!("CPUs of {} must be more than 0", self.name));
+        }
+        if self.cpu_shares.is_some_and(|shares| shares < 2) {
+            return Err(format!("CPU shares of {} must be at least 2", self.name));
+        }
+        if self.pids_limit == Some(0) {
+            return Err(format!("Pids limit of {} must be at least 1", self.name));
+        }
+        Ok(())
+    }
+
+    fn validate_runtime(&self) -> Result<(), String> {
+        let is_pair = |pair: &&String| pair.split_once('=').is_some_and(|(k, _)| !k.is_empty());
+        if let Some(label) = self.labels.iter().find(|label| !is_pair(label)) {
+            return Err(format!("Invalid label {}, use KEY=VALUE", label));
+        }
+        if let Some(sysctl) = self.sysctls.iter().find(|sysctl| !is_pair(sysctl)) {
+            return Err(format!("Invalid sysctl {}, use KEY=VALUE", sysctl));
+        }
+
+        let mut paths = self.working_dir.iter().chain(&self.tmpfs);
+        if let Some(path) = paths.find(|path| !path.starts_with('/')) {
+            return Err(format!("Path {} of {} must be absolute", path, self.name));
+        }
+
+        for host in &self.extra_hosts {
+            // Docker resolves host-gateway to the address of the host
+            let valid = host.split_once(':').is_some_and(|(name, ip)| {
+                !name.is_empty() && (ip == "host-gateway" || ip.parse::<IpAddr>().is_ok())
+            });
+            if !valid {
+                return Err(format!("Invalid host {}, use HOST:IP", host));
+            }
+        }
+        if let Some(server) = self.dns.iter().find(|s| s.parse::<IpAddr>().is_err()) {
+            return Err(format!("Invalid DNS server {}, use an IP address", server));
+        }
+        Ok(())
+    }
+
+    fn is_cidr(address: &str) -> bool {
+        let (ip, prefix) = match address.split_once('/') {
+            Some((ip, prefix)) => (ip, Some(prefix)),
+            None => (address, None),
+        };
+        let Ok(ip) = ip.parse::<IpAddr>() else {
+            return false;
+        };
+
+        let max_prefix = if ip.is_ipv4() { 32 } else { 128 };
+        match prefix {
+            Some(prefix) => prefix.parse::<u8>().is_ok_and(|p| p <= max_prefix),
+            None => true,
+        }
     }
 
     pub fn topological_sort_by_dependenceis(apps: &Vec<App>) -> Vec<App> {
@@ -444,6 +1327,40 @@ impl App {
         sorted
     }
 
+    /// Env files are read again on every deploy, possibly from another
+    /// working directory, so relative paths are stored as absolute paths.
+    pub fn absolute_path(path: &str) -> String {
+        match std::path::absolute(path) {
+            Ok(path) => path.display().to_string(),
+            Err(_) => path.to_owned(),
+        }
+    }
+
+    /// Takes all values of a flag that belong to the app defined at `index`.
+    fn take_values<T>(list: &mut Vec<Flag<T>>, index: usize) -> Vec<T> {
+        let mut values = vec![];
+        while let Some(flag) = list.pop() {
+            if flag.index > index {
+                values.push(flag.value);
+            } else {
+                list.push(flag);
+                break;
+            }
+        }
+        values.reverse();
+        values
+    }
+
+    /// Takes the value of a flag that may be given at most once per app.
+    fn take_value<T>(list: &mut Vec<Flag<T>>, index: usize, name: &str) -> Option<T> {
+        let mut values = Self::take_values(list, index);
+        if values.len() > 1 {
+            eprintln!("Error: App cannot have more than one {}", name);
+            std::process::exit(1);
+        }
+        values.pop()
+    }
+
     fn collect_flags<T>(args: &ArgMatches, flag: &'static str) -> Vec<Flag<T>>
     where
         T: Clone + std::marker::Send + std::marker::Sync + 'static,
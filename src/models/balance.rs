@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// How nginx spreads requests over the replicas of an app.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Balance {
+    /// Each replica in turn.
+    #[default]
+    RoundRobin,
+    /// The replica with the fewest active connections.
+    LeastConn,
+    /// The same replica for each client address, for sticky sessions.
+    IpHash,
+}
+
+impl Balance {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "round_robin" => Some(Self::RoundRobin),
+            "least_conn" => Some(Self::LeastConn),
+            "ip_hash" => Some(Self::IpHash),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RoundRobin => "round_robin",
+            Self::LeastConn => "least_conn",
+            Self::IpHash => "ip_hash",
+        }
+    }
+}
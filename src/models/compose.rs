@@ -0,0 +1,778 @@
+use std::{fs, path::Path};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use super::{
+    app::App,
+    health::{HealthCheck, Probe},
+    manifest::{AppManifest, Manifest},
+    memory::Memory,
+    project::Project,
+    publish::PortMapping,
+    restart::RestartPolicy,
+    ulimit::Ulimit,
+};
+use crate::utils::shell::Shell;
+
+/// The subset of the docker-compose file format that nbot can represent.
+/// Every key nbot does not understand is collected in `unsupported` so that
+/// it can be reported instead of silently dropped.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Compose {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub services: IndexMap<String, ComposeService>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub volumes: IndexMap<String, Option<Value>>,
+    #[serde(flatten)]
+    pub unsupported: IndexMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ComposeService {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<ComposeEnvironment>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<ComposeEnvFile>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<ComposeVolume>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<ComposeList>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub networks: Option<ComposeNetworks>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<ComposeCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entrypoint: Option<ComposeCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub privileged: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<ComposePairs>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cap_add: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cap_drop: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tmpfs: Option<ComposeStrings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sysctls: Option<ComposePairs>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub ulimits: IndexMap<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_hosts: Option<ComposePairs>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns: Option<ComposeStrings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shm_size: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mem_limit: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mem_reservation: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_shares: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pids_limit: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub healthcheck: Option<ComposeHealthcheck>,
+    #[serde(flatten)]
+    pub unsupported: IndexMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ComposeEnvironment {
+    List(Vec<String>),
+    Map(IndexMap<String, Option<Value>>),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ComposeEnvFile {
+    String(String),
+    List(Vec<String>),
+}
+
+/// A single string or a list of strings, e.g. `dns` or `tmpfs`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ComposeStrings {
+    String(String),
+    List(Vec<String>),
+}
+
+/// Key value pairs, written either as a list of `KEY=VALUE` strings or as a
+/// map, e.g. `labels` or `sysctls`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ComposePairs {
+    List(Vec<String>),
+    Map(IndexMap<String, Option<Value>>),
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ComposeHealthcheck {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test: Option<ComposeCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_period: Option<String>,
+    #[serde(flatten)]
+    pub unsupported: IndexMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ComposeVolume {
+    Short(String),
+    Long {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+        target: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        read_only: Option<bool>,
+        #[serde(flatten)]
+        unsupported: IndexMap<String, Value>,
+    },
+}
+
+/// Compose accepts both a plain list and a map keyed by name for several
+/// keys, e.g. `depends_on`. Only the names are used by nbot.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ComposeList {
+    List(Vec<String>),
+    Map(IndexMap<String, Value>),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ComposeNetworks {
+    List(Vec<String>),
+    Map(IndexMap<String, Option<ComposeServiceNetwork>>),
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ComposeServiceNetwork {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(flatten)]
+    pub unsupported: IndexMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ComposeCommand {
+    String(String),
+    List(Vec<String>),
+}
+
+impl ComposeCommand {
+    /// The arguments of the command. Compose splits strings like a shell
+    /// does, None if the string cannot be split.
+    fn args(self) -> Option<Vec<String>> {
+        match self {
+            ComposeCommand::String(line) => Shell::split(&line).ok(),
+            ComposeCommand::List(args) => Some(args),
+        }
+    }
+}
+
+impl ComposeStrings {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            ComposeStrings::String(value) => vec![value],
+            ComposeStrings::List(values) => values,
+        }
+    }
+}
+
+impl ComposePairs {
+    /// The pairs as `KEY{separator}VALUE`, None if a value of the map is not
+    /// a scalar. List entries are kept as they are.
+    fn into_vec(self, separator: char) -> Option<Vec<String>> {
+        match self {
+            ComposePairs::List(pairs) => Some(pairs),
+            ComposePairs::Map(map) => map
+                .into_iter()
+                .map(|(key, value)| match value {
+                    None | Some(Value::Null) => Some(format!("{key}{separator}")),
+                    Some(value) => {
+                        let value = yaml_scalar_to_string(&value)?;
+                        Some(format!("{key}{separator}{value}"))
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ComposeHealthcheck {
+    /// Compose runs the test like Docker does. Every test is a shell command
+    /// in nbot, so it is exported as `CMD-SHELL`, with `$` escaped from the
+    /// interpolation of compose.
+    fn from_health_check(check: &HealthCheck, port: &str) -> Self {
+        let command = check.command(port).replace('$', "$$");
+        Self {
+            test: Some(ComposeCommand::List(vec!["CMD-SHELL".to_owned(), command])),
+            interval: check.interval.to_owned(),
+            timeout: check.timeout.to_owned(),
+            retries: check.retries,
+            start_period: check.start_period.to_owned(),
+            unsupported: IndexMap::new(),
+        }
+    }
+
+    /// The health check as a command probe. None if the test disables the
+    /// health check of the image, which nbot cannot do, or if a duration is
+    /// not one nbot understands.
+    fn into_health_check(self) -> Option<HealthCheck> {
+        let command = match self.test? {
+            ComposeCommand::String(command) => command,
+            ComposeCommand::List(test) => match test.split_first() {
+                Some((kind, command)) if kind == "CMD-SHELL" => command.join(" "),
+                Some((kind, args)) if kind == "CMD" => Shell::join(args),
+                _ => return None,
+            },
+        };
+        let check = HealthCheck {
+            probe: Probe::Exec {
+                command: command.replace("$$", "$"),
+            },
+            interval: self.interval,
+            timeout: self.timeout,
+            retries: self.retries,
+            start_period: self.start_period,
+        };
+        check.validate().ok().map(|_| check)
+    }
+}
+
+impl ComposeList {
+    fn names(&self) -> Vec<String> {
+        match self {
+            ComposeList::List(names) => names.to_owned(),
+            ComposeList::Map(map) => map.keys().cloned().collect(),
+        }
+    }
+}
+
+impl Compose {
+    pub fn from_file(path: &str) -> Self {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Error reading compose file {}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+
+        match serde_yaml::from_str(&content) {
+            Ok(compose) => compose,
+            Err(e) => {
+                eprintln!("Error parsing compose file {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    /// Renders a stored project as a compose file that can be run locally
+    /// with `docker compose up`. Apps exposed through nginx publish their
+    /// port on the same host port instead.
+    pub fn from_project(project: &Project) -> Self {
+        let mut services = IndexMap::new();
+        let mut volumes = IndexMap::new();
+
+        for app in &project.apps {
+            for volume in &app.volumes {
+                let source = volume.split(':').next().unwrap_or_default();
+                if !source.is_empty() && !source.starts_with(['/', '.']) {
+                    volumes.insert(source.to_owned(), None);
+                }
+            }
+
+            let networks = if app.network_aliases.is_empty() {
+                None
+            } else {
+                let network = ComposeServiceNetwork {
+                    aliases: app.network_aliases.to_owned(),
+                    ..Default::default()
+                };
+                Some(ComposeNetworks::Map(IndexMap::from([(
+                    "default".to_owned(),
+                    Some(network),
+                )])))
+            };
+
+            let mut ports = match &app.port {
+                Some(port) if app.is_public() => vec![Value::String(format!("{port}:{port}"))],
+                _ => vec![],
+            };
+            for stream in &app.streams {
+                ports.push(Value::String(format!(
+                    "{}:{}/{}",
+                    stream.port,
+                    stream.target_port(),
+                    stream.protocol.name()
+                )));
+            }
+            ports.extend(app.publish.iter().map(|m| Value::String(m.to_string())));
+
+            let mut ulimits = IndexMap::new();
+            for ulimit in &app.ulimits {
+                let limit = match ulimit.hard {
+                    Some(hard) => Value::Mapping(
+                        [
+                            (Value::from("soft"), Value::from(ulimit.soft)),
+                            (Value::from("hard"), Value::from(hard)),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    None => Value::from(ulimit.soft),
+                };
+                ulimits.insert(ulimit.name.to_owned(), limit);
+            }
+            let list = |values: &Vec<String>| (!values.is_empty()).then(|| values.to_owned());
+            let memory = |memory: Option<Memory>| memory.map(|m| Value::String(m.to_string()));
+
+            let service = ComposeService {
+                image: Some(app.image.to_owned()),
+                environment: (!app.env_vars.is_empty())
+                    .then(|| ComposeEnvironment::List(app.env_vars.to_owned())),
+                env_file: (!app.env_files.is_empty())
+                    .then(|| ComposeEnvFile::List(app.env_files.to_owned())),
+                volumes: app
+                    .volumes
+                    .iter()
+                    .map(|v| ComposeVolume::Short(v.to_owned()))
+                    .collect(),
+                depends_on: (!app.depends_on.is_empty())
+                    .then(|| ComposeList::List(app.depends_on.to_owned())),
+                networks,
+                command: app.cmd.to_owned().map(ComposeCommand::List),
+                entrypoint: app.entrypoint.to_owned().map(ComposeCommand::List),
+                privileged: app.privileged.then_some(true),
+                restart: app.restart.map(|policy| policy.to_string()),
+                ports,
+                user: app.user.to_owned().map(Value::String),
+                working_dir: app.working_dir.to_owned(),
+                hostname: app.hostname.to_owned(),
+                labels: list(&app.labels).map(ComposePairs::List),
+                cap_add: app.cap_add.to_owned(),
+                cap_drop: app.cap_drop.to_owned(),
+                read_only: app.read_only.then_some(true),
+                tmpfs: list(&app.tmpfs).map(ComposeStrings::List),
+                sysctls: list(&app.sysctls).map(ComposePairs::List),
+                ulimits,
+                extra_hosts: list(&app.extra_hosts).map(ComposePairs::List),
+                dns: list(&app.dns).map(ComposeStrings::List),
+                shm_size: memory(app.shm_size),
+                mem_limit: memory(app.memory),
+                mem_reservation: memory(app.memory_reservation),
+                cpus: app.cpus.map(Value::from),
+                cpu_shares: app.cpu_shares.map(Value::from),
+                pids_limit: app.pids_limit.map(Value::from),
+                healthcheck: app.health_check.as_ref().map(|check| {
+                    let port = app.port.as_deref().unwrap_or("80");
+                    ComposeHealthcheck::from_health_check(check, port)
+                }),
+                unsupported: IndexMap::new(),
+            };
+
+            services.insert(app.name.to_owned(), service);
+        }
+
+        Self {
+            name: Some(project.name.to_owned()),
+            services,
+            volumes,
+            unsupported: IndexMap::new(),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        match serde_yaml::to_string(self) {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                eprintln!("Error rendering compose file: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    /// Translates the compose file into a manifest for the project `name`.
+    /// Relative bind mounts are resolved against `base_dir`. Returns the
+    /// manifest together with a list of keys that could not be represented.
+    pub fn into_manifest(self, name: &str, base_dir: &Path) -> (Manifest, Vec<String>) {
+        // `version` is obsolete in the compose specification and safe to drop
+        let mut unsupported: Vec<String> = self
+            .unsupported
+            .keys()
+            .filter(|k| k.as_str() != "version")
+            .cloned()
+            .collect();
+
+        for (volume, config) in &self.volumes {
+            let has_options = match config {
+                Some(Value::Mapping(options)) => !options.is_empty(),
+                Some(Value::Null) | None => false,
+                Some(_) => true,
+            };
+            if has_options {
+                unsupported.push(format!("volumes.{volume}"));
+            }
+        }
+
+        let mut apps = vec![];
+        for (service_name, service) in self.services {
+            let prefix = format!("services.{service_name}");
+            unsupported.extend(service.unsupported.keys().map(|k| format!("{prefix}.{k}")));
+
+            // Only short syntax with a host port maps onto published ports
+            let mut publish = vec![];
+            for (index, port) in service.ports.iter().enumerate() {
+                if let Value::String(port) = port {
+                    if let Ok(mapping) = PortMapping::parse(port) {
+                        publish.push(mapping);
+                        continue;
+                    }
+                }
+                let port = match port {
+                    Value::Mapping(options) => {
+                        options.get("target").and_then(yaml_scalar_to_string)
+                    }
+                    port => yaml_scalar_to_string(port),
+                };
+                unsupported.push(format!(
+                    "{prefix}.ports.{}",
+                    port.unwrap_or_else(|| index.to_string())
+                ));
+            }
+
+            let Some(image) = service.image else {
+                eprintln!("Error: Service {service_name} has no image. Build the image first and reference it with the image key.");
+                std::process::exit(1);
+            };
+
+            let mut env_vars = vec![];
+            match service.environment {
+                Some(ComposeEnvironment::List(list)) => {
+                    env_vars.extend(list);
+                }
+                Some(ComposeEnvironment::Map(map)) => {
+                    for (key, value) in map {
+                        match value {
+                            // A key without a value is passed through from the host
+                            None | Some(Value::Null) => env_vars.push(key),
+                            Some(value) => match yaml_scalar_to_string(&value) {
+                                Some(value) => env_vars.push(format!("{key}={value}")),
+                                None => unsupported.push(format!("{prefix}.environment.{key}")),
+                            },
+                        }
+                    }
+                }
+                None => {}
+            }
+
+            let env_files = match service.env_file {
+                Some(ComposeEnvFile::String(path)) => vec![path],
+                Some(ComposeEnvFile::List(paths)) => paths,
+                None => vec![],
+            };
+            let env_files = env_files
+                .iter()
+                .map(|path| App::absolute_path(&base_dir.join(path).to_string_lossy()))
+                .collect();
+
+            let mut volumes = vec![];
+            for volume in service.volumes {
+                match volume {
+                    ComposeVolume::Short(volume) => volumes.push(resolve_bind(&volume, base_dir)),
+                    ComposeVolume::Long {
+                        source,
+                        target,
+                        read_only,
+                        unsupported: extra,
+                    } => {
+                        unsupported.extend(
+                            extra
+                                .keys()
+                                .filter(|k| k.as_str() != "type")
+                                .map(|k| format!("{prefix}.volumes.{target}.{k}")),
+                        );
+                        let Some(source) = source else {
+                            unsupported.push(format!("{prefix}.volumes.{target}"));
+                            continue;
+                        };
+                        let mut volume = format!("{source}:{target}");
+                        if read_only.unwrap_or(false) {
+                            volume.push_str(":ro");
+                        }
+                        volumes.push(resolve_bind(&volume, base_dir));
+                    }
+                }
+            }
+
+            // nbot starts dependencies first, but does not wait for a condition
+            if let Some(ComposeList::Map(dependencies)) = &service.depends_on {
+                for (dependency, config) in dependencies {
+                    if let Value::Mapping(options) = config {
+                        unsupported.extend(options.keys().filter_map(|k| {
+                            let key = yaml_scalar_to_string(k)?;
+                            Some(format!("{prefix}.depends_on.{dependency}.{key}"))
+                        }));
+                    }
+                }
+            }
+            let depends_on = service.depends_on.map(|d| d.names()).unwrap_or_default();
+
+            let mut network_aliases = vec![];
+            match service.networks {
+                // Apps only join the networks of their project
+                Some(ComposeNetworks::List(networks)) => unsupported.extend(
+                    networks
+                        .iter()
+                        .filter(|network| network.as_str() != "default")
+                        .map(|network| format!("{prefix}.networks.{network}")),
+                ),
+                Some(ComposeNetworks::Map(networks)) => {
+                    for (network, config) in networks {
+                        let Some(config) = config else {
+                            continue;
+                        };
+                        network_aliases.extend(config.aliases);
+                        unsupported.extend(
+                            config
+                                .unsupported
+                                .keys()
+                                .map(|k| format!("{prefix}.networks.{network}.{k}")),
+                        );
+                    }
+                }
+                None => {}
+            }
+
+            let cmd = service.command.and_then(|command| {
+                let args = command.args();
+                if args.is_none() {
+                    unsupported.push(format!("{prefix}.command"));
+                }
+                args
+            });
+            let entrypoint = service.entrypoint.and_then(|command| {
+                let args = command.args();
+                if args.is_none() {
+                    unsupported.push(format!("{prefix}.entrypoint"));
+                }
+                args
+            });
+
+            let restart = service.restart.and_then(|policy| {
+                let policy = RestartPolicy::parse(&policy).ok();
+                if policy.is_none() {
+                    unsupported.push(format!("{prefix}.restart"));
+                }
+                policy
+            });
+
+            let user = service.user.and_then(|user| {
+                let user = yaml_scalar_to_string(&user);
+                if user.is_none() {
+                    unsupported.push(format!("{prefix}.user"));
+                }
+                user
+            });
+
+            let mut pairs = |key: &str, pairs: Option<ComposePairs>, separator: char| {
+                let pairs = pairs.map(|pairs| pairs.into_vec(separator));
+                if let Some(None) = pairs {
+                    unsupported.push(format!("{prefix}.{key}"));
+                }
+                pairs.flatten().unwrap_or_default()
+            };
+            let labels = pairs("labels", service.labels, '=');
+            let sysctls = pairs("sysctls", service.sysctls, '=');
+            // Newer compose files may also write hosts as HOST=IP
+            let extra_hosts = pairs("extra_hosts", service.extra_hosts, ':')
+                .into_iter()
+                .map(|host| match (host.find('='), host.find(':')) {
+                    (Some(equals), colon) if colon.is_none_or(|colon| equals < colon) => {
+                        host.replacen('=', ":", 1)
+                    }
+                    _ => host,
+                })
+                .collect();
+
+            let mut ulimits = vec![];
+            for (name, limit) in service.ulimits {
+                let spec = match &limit {
+                    Value::Number(soft) => Some(format!("{name}={soft}")),
+                    Value::Mapping(limits) => match (limits.get("soft"), limits.get("hard")) {
+                        (Some(Value::Number(soft)), Some(Value::Number(hard))) => {
+                            Some(format!("{name}={soft}:{hard}"))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match spec.and_then(|spec| Ulimit::parse(&spec).ok()) {
+                    Some(ulimit) => ulimits.push(ulimit),
+                    None => unsupported.push(format!("{prefix}.ulimits.{name}")),
+                }
+            }
+
+            let mut memory = |key: &str, value: Option<Value>| {
+                let memory = value.map(|value| match value {
+                    Value::Number(bytes) => bytes.as_u64().map(|bytes| Memory { bytes }),
+                    Value::String(memory) => Memory::parse(&memory).ok(),
+                    _ => None,
+                });
+                if let Some(None) = memory {
+                    unsupported.push(format!("{prefix}.{key}"));
+                }
+                memory.flatten()
+            };
+            let shm_size = memory("shm_size", service.shm_size);
+            let mem_limit = memory("mem_limit", service.mem_limit);
+            let mem_reservation = memory("mem_reservation", service.mem_reservation);
+
+            let cpus = service.cpus.and_then(|cpus| {
+                let parsed = match &cpus {
+                    Value::Number(cpus) => cpus.as_f64(),
+                    Value::String(cpus) => cpus.parse().ok(),
+                    _ => None,
+                };
+                if parsed.is_none() {
+                    unsupported.push(format!("{prefix}.cpus"));
+                }
+                parsed
+            });
+            let mut count = |key: &str, value: Option<Value>| {
+                let count = value.map(|value| match value {
+                    Value::Number(count) => count.as_u64().and_then(|c| u32::try_from(c).ok()),
+                    Value::String(count) => count.parse().ok(),
+                    _ => None,
+                });
+                if let Some(None) = count {
+                    unsupported.push(format!("{prefix}.{key}"));
+                }
+                count.flatten()
+            };
+            let cpu_shares = count("cpu_shares", service.cpu_shares);
+            // -1, which compose uses for no limit, is reported as unsupported
+            let pids_limit = count("pids_limit", service.pids_limit);
+
+            let health_check = service.healthcheck.and_then(|check| {
+                unsupported.extend(
+                    check
+                        .unsupported
+                        .keys()
+                        .map(|k| format!("{prefix}.healthcheck.{k}")),
+                );
+                let check = check.into_health_check();
+                if check.is_none() {
+                    unsupported.push(format!("{prefix}.healthcheck"));
+                }
+                check
+            });
+
+            apps.push(AppManifest {
+                name: service_name,
+                image,
+                env_vars,
+                env_files,
+                volumes,
+                depends_on,
+                privileged: service.privileged.unwrap_or(false),
+                restart,
+                publish,
+                entrypoint,
+                network_aliases,
+                cmd,
+                user,
+                working_dir: service.working_dir,
+                hostname: service.hostname,
+                labels,
+                cap_add: service.cap_add,
+                cap_drop: service.cap_drop,
+                read_only: service.read_only.unwrap_or(false),
+                tmpfs: service
+                    .tmpfs
+                    .map(ComposeStrings::into_vec)
+                    .unwrap_or_default(),
+                sysctls,
+                ulimits,
+                extra_hosts,
+                dns: service
+                    .dns
+                    .map(ComposeStrings::into_vec)
+                    .unwrap_or_default(),
+                shm_size,
+                memory: mem_limit,
+                memory_reservation: mem_reservation,
+                cpus,
+                cpu_shares,
+                pids_limit,
+                health_check,
+                ..Default::default()
+            });
+        }
+
+        let manifest = Manifest {
+            name: name.to_owned(),
+            apps,
+        };
+
+        (manifest, unsupported)
+    }
+}
+
+fn yaml_scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.to_owned()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Docker requires absolute host paths for bind mounts, while compose allows
+/// paths relative to the compose file.
+fn resolve_bind(volume: &str, base_dir: &Path) -> String {
+    if !volume.starts_with('.') {
+        return volume.to_owned();
+    }
+
+    let (source, rest) = match volume.split_once(':') {
+        Some((source, rest)) => (source, format!(":{rest}")),
+        None => (volume, String::new()),
+    };
+
+    let source = base_dir.join(source.trim_start_matches("./"));
+    let source = source.canonicalize().unwrap_or(source);
+    format!("{}{}", source.display(), rest)
+}
@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How the health of an app is probed from inside its container.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Probe {
+    /// A request to a path of the app, which has to answer with `status`.
+    /// The image needs curl or wget.
+    Http {
+        path: String,
+        #[serde(default = "Probe::default_status")]
+        status: u16,
+    },
+    /// A connection to the port of the app. The image needs nc.
+    Tcp,
+    /// A shell command, which has to exit with 0.
+    Exec { command: String },
+}
+
+impl Probe {
+    fn default_status() -> u16 {
+        200
+    }
+}
+
+/// A health check, set as the Docker `HEALTHCHECK` of the app's containers.
+/// Deploys wait until the containers are healthy. Unset durations and
+/// retries use nbot's defaults: 10s interval, 5s timeout and 3 retries.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct HealthCheck {
+    #[serde(flatten)]
+    pub probe: Probe,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    /// Time the app gets to start, in which failed checks do not count.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_period: Option<String>,
+}
+
+impl HealthCheck {
+    const OPTIONS: [&'static str; 4] = ["interval", "timeout", "retries", "start_period"];
+
+    /// Parses `http:PATH[=STATUS]`, `tcp` or `exec:COMMAND`, followed by
+    /// `,interval=`, `,timeout=`, `,retries=` and `,start_period=` options,
+    /// e.g. `http:/healthz,interval=5s` or `exec:pg_isready -U postgres`.
+    /// Commands may contain commas, only known options at the end are split
+    /// off.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts: Vec<&str> = spec.split(',').collect();
+        let mut options = vec![];
+        while parts.len() > 1 {
+            let last = parts[parts.len() - 1];
+            match last.split_once('=') {
+                Some((key, value)) if Self::OPTIONS.contains(&key.trim()) => {
+                    options.insert(0, (key.trim(), value.trim()));
+                    parts.pop();
+                }
+                _ => break,
+            }
+        }
+        let probe = parts.join(",");
+
+        let probe = match probe.split_once(':') {
+            Some(("http", target)) => {
+                // Query strings may contain = as well, only a number is a status
+                let (path, status) = match target.rsplit_once('=') {
+                    Some((path, status)) if status.parse::<u16>().is_ok() => {
+                        (path, status.parse().unwrap())
+                    }
+                    _ => (target, Probe::default_status()),
+                };
+                Probe::Http {
+                    path: path.to_owned(),
+                    status,
+                }
+            }
+            Some(("exec", command)) => Probe::Exec {
+                command: command.to_owned(),
+            },
+            None if probe == "tcp" => Probe::Tcp,
+            _ => {
+                return Err(format!(
+                    "Invalid health check {}, use http:PATH[=STATUS], tcp or exec:COMMAND",
+                    spec
+                ))
+            }
+        };
+
+        let mut check = HealthCheck {
+            probe,
+            interval: None,
+            timeout: None,
+            retries: None,
+            start_period: None,
+        };
+        for (key, value) in options {
+            match key {
+                "interval" => check.interval = Some(value.to_owned()),
+                "timeout" => check.timeout = Some(value.to_owned()),
+                "start_period" => check.start_period = Some(value.to_owned()),
+                _ => match value.parse() {
+                    Ok(retries) => check.retries = Some(retries),
+                    Err(_) => return Err(format!("Invalid retries {} in health check", value)),
+                },
+            }
+        }
+
+        check.validate()?;
+        Ok(check)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.probe {
+            Probe::Http { path, status } => {
+                if !path.starts_with('/') {
+                    return Err(format!("Health check path {} must start with /", path));
+                }
+                if !(100..600).contains(status) {
+                    return Err(format!("Invalid status {} in health check", status));
+                }
+            }
+            Probe::Exec { command } if command.trim().is_empty() => {
+                return Err("Health check command cannot be empty".to_owned());
+            }
+            _ => {}
+        }
+
+        let durations = [&self.interval, &self.timeout, &self.start_period];
+        for duration in durations.into_iter().flatten() {
+            Self::duration(duration)?;
+        }
+        Ok(())
+    }
+
+    /// The shell command Docker runs in the container. `port` is the port
+    /// the app listens on.
+    pub fn command(&self, port: &str) -> String {
+        match &self.probe {
+            Probe::Http { path, status } => {
+                let url = format!("http://localhost:{}{}", port, path);
+                format!(
+                    "if command -v curl >/dev/null; then \
+                     test \"$(curl -s -o /dev/null -w '%{{http_code}}' '{url}')\" = {status}; \
+                     else wget -q -S -O /dev/null '{url}' 2>&1 | grep -q 'HTTP/[0-9.]* {status}'; fi"
+                )
+            }
+            Probe::Tcp => format!("nc -z localhost {}", port),
+            Probe::Exec { command } => command.to_owned(),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.duration_or(&self.interval, Duration::from_secs(10))
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.duration_or(&self.timeout, Duration::from_secs(5))
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.retries.unwrap_or(3)
+    }
+
+    pub fn start_period(&self) -> Duration {
+        self.duration_or(&self.start_period, Duration::ZERO)
+    }
+
+    /// Longest time a new container can take to be reported healthy or
+    /// unhealthy.
+    pub fn max_wait(&self) -> Duration {
+        let attempts = self.retries() + 1;
+        self.start_period() + (self.interval() + self.timeout()) * attempts
+    }
+
+    fn duration_or(&self, value: &Option<String>, default: Duration) -> Duration {
+        value
+            .as_ref()
+            .and_then(|value| Self::duration(value).ok())
+            .unwrap_or(default)
+    }
+
+    /// Parses a duration such as `500ms`, `10s`, `5m` or `1h`.
+    fn duration(value: &str) -> Result<Duration, String> {
+        let digits = value.chars().take_while(char::is_ascii_digit).count();
+        let number: u64 = value[..digits]
+            .parse()
+            .map_err(|_| format!("Invalid duration {} in health check", value))?;
+        match &value[digits..] {
+            "ms" => Ok(Duration::from_millis(number)),
+            "s" => Ok(Duration::from_secs(number)),
+            "m" => Ok(Duration::from_secs(number * 60)),
+            "h" => Ok(Duration::from_secs(number * 3600)),
+            _ => Err(format!(
+                "Invalid duration {} in health check, use ms, s, m or h",
+                value
+            )),
+        }
+    }
+}
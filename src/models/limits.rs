@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use super::route::Route;
+
+/// Limits the request rate of each client address, optionally only below a
+/// path, e.g. 10 requests per second with a burst of 20 on `/login`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// Requests per second or minute, e.g. `10r/s` or `300r/m`.
+    pub rate: String,
+    /// Requests above the rate that are queued instead of rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+    /// Serve queued requests immediately instead of spacing them out.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub nodelay: bool,
+    /// Request path the limit applies to. Defaults to everything the app serves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl RateLimit {
+    /// Parses `RATE[,burst=N][,nodelay][,path=PATH]`, e.g.
+    /// `10r/s,burst=20,path=/login`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec.split(',');
+        let mut limit = RateLimit {
+            rate: parts.next().unwrap_or_default().to_owned(),
+            burst: None,
+            nodelay: false,
+            path: None,
+        };
+
+        for option in parts {
+            match option.split_once('=') {
+                Some(("burst", burst)) => match burst.parse() {
+                    Ok(burst) => limit.burst = Some(burst),
+                    Err(_) => return Err(format!("Invalid burst {} in {}", burst, spec)),
+                },
+                Some(("path", path)) if path.starts_with('/') => {
+                    limit.path = Some(Route::normalize_path(path))
+                }
+                Some(("path", path)) => limit.path = Some(path.to_owned()),
+                None if option == "nodelay" => limit.nodelay = true,
+                _ => return Err(format!("Unknown rate limit option {} in {}", option, spec)),
+            }
+        }
+
+        limit.validate()?;
+        Ok(limit)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let number = self
+            .rate
+            .strip_suffix("r/s")
+            .or(self.rate.strip_suffix("r/m"));
+        if !number.is_some_and(|n| n.parse::<u32>().is_ok_and(|n| n > 0)) {
+            return Err(format!(
+                "Invalid rate {}, use requests per second or minute, e.g. 10r/s",
+                self.rate
+            ));
+        }
+        if let Some(path) = self.path.as_ref().filter(|path| !path.starts_with('/')) {
+            return Err(format!("Rate limit path {} must start with /", path));
+        }
+        Ok(())
+    }
+
+    /// Describes the limit for `nbot inspect`.
+    pub fn describe(&self) -> String {
+        let mut description = self.rate.to_owned();
+        if let Some(burst) = self.burst {
+            description.push_str(&format!(", burst {}", burst));
+        }
+        if self.nodelay {
+            description.push_str(", nodelay");
+        }
+        description.push_str(&format!(" on {}", self.path.as_deref().unwrap_or("/")));
+        description
+    }
+}
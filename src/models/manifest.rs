@@ -0,0 +1,396 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{utils::shell::Shell, APP_STATE};
+
+use super::{
+    app::App, balance::Balance, health::HealthCheck, limits::RateLimit, memory::Memory,
+    project::Project, proxy::ProxyOptions, publish::PortMapping, redirect::Redirect,
+    restart::RestartPolicy, route::Route, stream::Stream, tls::TlsMode, ulimit::Ulimit,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ManifestFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ManifestFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        Self::from_name(extension)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Declarative description of a project. Every field maps one-to-one onto
+/// [`Project`] and [`App`], except for values nbot derives itself, such as
+/// container names.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub name: String,
+    #[serde(default)]
+    pub apps: Vec<AppManifest>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppManifest {
+    pub name: String,
+    pub image: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_vars: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_files: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domains: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirects: Vec<Redirect>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routes: Vec<Route>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub streams: Vec<Stream>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub publish: Vec<PortMapping>,
+    #[serde(default, skip_serializing_if = "ProxyOptions::is_default")]
+    pub proxy: ProxyOptions,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rate_limits: Vec<RateLimit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conn_limit: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replicas: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<Balance>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<HealthCheck>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart: Option<RestartPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Memory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_reservation: Option<Memory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_shares: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pids_limit: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_cert: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_key: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub privileged: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network_aliases: Vec<String>,
+    /// A list of arguments, or a command line split like a shell does.
+    #[serde(
+        default,
+        deserialize_with = "Shell::deserialize_line",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cmd: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "Shell::deserialize_line",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub entrypoint: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cap_add: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cap_drop: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tmpfs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sysctls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ulimits: Vec<Ulimit>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_hosts: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dns: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shm_size: Option<Memory>,
+}
+
+impl Manifest {
+    pub fn from_file(path: &str) -> Self {
+        let Some(format) = ManifestFormat::from_path(path) else {
+            eprintln!("Error: Unsupported manifest format. Use a .toml, .yaml, .yml or .json file");
+            std::process::exit(1);
+        };
+
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Error reading manifest {}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+
+        let mut manifest = match Self::parse(&content, format) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                eprintln!("Error parsing manifest {}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+
+        // Files are relative to the manifest, not the working directory
+        let base_dir = Path::new(path).parent().unwrap_or(Path::new("."));
+        let resolve = |file: &str| App::absolute_path(&base_dir.join(file).to_string_lossy());
+        for app in &mut manifest.apps {
+            for env_file in &mut app.env_files {
+                *env_file = resolve(env_file);
+            }
+            app.tls_cert = app.tls_cert.as_deref().map(resolve);
+            app.tls_key = app.tls_key.as_deref().map(resolve);
+        }
+
+        manifest
+    }
+
+    pub fn parse(content: &str, format: ManifestFormat) -> Result<Self, String> {
+        match format {
+            ManifestFormat::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+            ManifestFormat::Yaml => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+            ManifestFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn render(&self, format: ManifestFormat) -> String {
+        let rendered = match format {
+            ManifestFormat::Toml => toml::to_string_pretty(self).map_err(|e| e.to_string()),
+            ManifestFormat::Yaml => serde_yaml::to_string(self).map_err(|e| e.to_string()),
+            ManifestFormat::Json => serde_json::to_string_pretty(self).map_err(|e| e.to_string()),
+        };
+
+        match rendered {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                eprintln!("Error rendering manifest: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    pub fn from_project(project: &Project) -> Self {
+        Self {
+            name: project.name.to_owned(),
+            apps: project.apps.iter().map(AppManifest::from_app).collect(),
+        }
+    }
+
+    pub fn into_project(self) -> Project {
+        if self.apps.is_empty() {
+            eprintln!("Error: Project must have at least one app");
+            std::process::exit(1);
+        }
+
+        let apps: Vec<App> = self
+            .apps
+            .into_iter()
+            .map(|app| app.into_app(&self.name))
+            .collect();
+
+        App::validate(&apps);
+
+        Project {
+            name: self.name,
+            apps,
+        }
+    }
+}
+
+impl AppManifest {
+    pub fn from_app(app: &App) -> Self {
+        Self {
+            name: app.name.to_owned(),
+            image: app.image.to_owned(),
+            port: app.port.to_owned(),
+            env_vars: app.env_vars.to_owned(),
+            env_files: app.env_files.to_owned(),
+            secrets: app.secrets.to_owned(),
+            volumes: app.volumes.to_owned(),
+            depends_on: app.depends_on.to_owned(),
+            domains: app.domains.to_owned(),
+            redirects: app.redirects.to_owned(),
+            routes: app.routes.to_owned(),
+            streams: app.streams.to_owned(),
+            publish: app.publish.to_owned(),
+            proxy: app.proxy.to_owned(),
+            allow: app.allow.to_owned(),
+            deny: app.deny.to_owned(),
+            rate_limits: app.rate_limits.to_owned(),
+            conn_limit: app.conn_limit,
+            replicas: app.replicas,
+            balance: app.balance,
+            health_check: app.health_check.to_owned(),
+            restart: app.restart,
+            memory: app.memory,
+            memory_reservation: app.memory_reservation,
+            cpus: app.cpus,
+            cpu_shares: app.cpu_shares,
+            pids_limit: app.pids_limit,
+            email: app.email.to_owned(),
+            tls: app.tls,
+            tls_cert: app.tls_cert.to_owned(),
+            tls_key: app.tls_key.to_owned(),
+            privileged: app.privileged,
+            network_aliases: app.network_aliases.to_owned(),
+            cmd: app.cmd.to_owned(),
+            user: app.user.to_owned(),
+            working_dir: app.working_dir.to_owned(),
+            entrypoint: app.entrypoint.to_owned(),
+            hostname: app.hostname.to_owned(),
+            labels: app.labels.to_owned(),
+            cap_add: app.cap_add.to_owned(),
+            cap_drop: app.cap_drop.to_owned(),
+            read_only: app.read_only,
+            tmpfs: app.tmpfs.to_owned(),
+            sysctls: app.sysctls.to_owned(),
+            ulimits: app.ulimits.to_owned(),
+            extra_hosts: app.extra_hosts.to_owned(),
+            dns: app.dns.to_owned(),
+            shm_size: app.shm_size,
+        }
+    }
+
+    pub fn into_app(self, project: &str) -> App {
+        if self.image.is_empty() {
+            eprintln!("Error: App must have an image");
+            std::process::exit(1);
+        }
+
+        let domains = self.domains.filter(|domains| !domains.is_empty());
+
+        let routes: Vec<Route> = self
+            .routes
+            .into_iter()
+            .map(|route| Route {
+                path: Route::normalize_path(&route.path),
+                ..route
+            })
+            .collect();
+
+        let rate_limits: Vec<RateLimit> = self
+            .rate_limits
+            .into_iter()
+            .map(|limit| RateLimit {
+                path: limit.path.map(|path| match path.starts_with('/') {
+                    true => Route::normalize_path(&path),
+                    false => path,
+                }),
+                ..limit
+            })
+            .collect();
+
+        let checks = self.health_check.iter().map(HealthCheck::validate);
+        for result in self.redirects.iter().map(Redirect::validate).chain(checks) {
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+
+        let mut port = self.port;
+        let mut tls = None;
+        if domains.is_some() || !routes.is_empty() || !self.redirects.is_empty() {
+            port = port.or(Some("80".to_owned()));
+            tls = Some(self.tls.unwrap_or(TlsMode::Letsencrypt));
+        }
+        App::validate_tls(&tls, &self.email, &self.tls_cert, &self.tls_key);
+
+        App {
+            container_name: format!("{}{}_{}", APP_STATE.container_prefix, project, self.name),
+            name: self.name,
+            image: self.image,
+            image_id: None,
+            port,
+            env_vars: self.env_vars,
+            env_files: self.env_files,
+            secrets: self.secrets,
+            volumes: self.volumes,
+            depends_on: self.depends_on,
+            domains,
+            redirects: self.redirects,
+            routes,
+            streams: self.streams,
+            publish: self.publish,
+            proxy: self.proxy,
+            allow: self.allow,
+            deny: self.deny,
+            rate_limits,
+            conn_limit: self.conn_limit,
+            replicas: self.replicas,
+            balance: self.balance,
+            health_check: self.health_check,
+            restart: self.restart,
+            memory: self.memory,
+            memory_reservation: self.memory_reservation,
+            cpus: self.cpus,
+            cpu_shares: self.cpu_shares,
+            pids_limit: self.pids_limit,
+            email: self.email,
+            tls,
+            tls_cert: self.tls_cert,
+            tls_key: self.tls_key,
+            privileged: self.privileged,
+            network_aliases: self.network_aliases,
+            cmd: self.cmd,
+            user: self.user,
+            working_dir: self.working_dir,
+            entrypoint: self.entrypoint,
+            hostname: self.hostname,
+            labels: self.labels,
+            cap_add: self.cap_add,
+            cap_drop: self.cap_drop,
+            read_only: self.read_only,
+            tmpfs: self.tmpfs,
+            sysctls: self.sysctls,
+            ulimits: self.ulimits,
+            extra_hosts: self.extra_hosts,
+            dns: self.dns,
+            shm_size: self.shm_size,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// An amount of memory, written the way Docker does: a number of bytes with
+/// an optional `b`, `k`, `m` or `g` unit, e.g. `512m` or `1g`.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Memory {
+    pub bytes: u64,
+}
+
+impl Memory {
+    const UNITS: [(&'static str, u64); 4] =
+        [("g", 1 << 30), ("m", 1 << 20), ("k", 1 << 10), ("b", 1)];
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let lower = value.trim().to_lowercase();
+        let number_length = lower
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(lower.len());
+        let (number, unit) = lower.split_at(number_length);
+        let unit = unit
+            .strip_suffix('b')
+            .filter(|unit| !unit.is_empty())
+            .unwrap_or(unit);
+
+        let multiplier = match unit {
+            "" => 1,
+            _ => match Self::UNITS.iter().find(|(name, _)| *name == unit) {
+                Some((_, multiplier)) => *multiplier,
+                None => return Err(Self::invalid(value)),
+            },
+        };
+        let number: f64 = number.parse().map_err(|_| Self::invalid(value))?;
+
+        Ok(Self {
+            bytes: (number * multiplier as f64) as u64,
+        })
+    }
+
+    /// A size for humans, such as `118.4MiB`, as `docker stats` prints it.
+    pub fn human(bytes: u64) -> String {
+        for (unit, multiplier) in [("GiB", 1 << 30), ("MiB", 1 << 20), ("KiB", 1 << 10)] {
+            if bytes >= multiplier {
+                return format!("{:.1}{}", bytes as f64 / multiplier as f64, unit);
+            }
+        }
+        format!("{}B", bytes)
+    }
+
+    fn invalid(value: &str) -> String {
+        format!(
+            "Invalid memory {}, use a number of bytes with b, k, m or g, e.g. 512m",
+            value
+        )
+    }
+}
+
+impl fmt::Display for Memory {
+    /// The largest unit that represents the size exactly.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (unit, multiplier) in Self::UNITS {
+            if self.bytes > 0 && self.bytes.is_multiple_of(multiplier) {
+                return write!(f, "{}{}", self.bytes / multiplier, unit);
+            }
+        }
+        write!(f, "{}", self.bytes)
+    }
+}
+
+impl TryFrom<String> for Memory {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl From<Memory> for String {
+    fn from(memory: Memory) -> Self {
+        memory.to_string()
+    }
+}
@@ -1,5 +1,31 @@
 mod app;
+mod balance;
+mod compose;
+mod health;
+mod limits;
+mod manifest;
+mod memory;
 mod project;
+mod proxy;
+mod publish;
+mod redirect;
+mod restart;
+mod route;
+mod stream;
+mod tls;
+mod ulimit;
 
 pub use app::App;
+pub use balance::Balance;
+pub use compose::Compose;
+pub use health::HealthCheck;
+pub use limits::RateLimit;
+pub use manifest::{Manifest, ManifestFormat};
+pub use memory::Memory;
 pub use project::Project;
+pub use proxy::ProxyOptions;
+pub use publish::PortMapping;
+pub use redirect::Redirect;
+pub use restart::RestartPolicy;
+pub use stream::{Protocol, Stream};
+pub use tls::TlsMode;
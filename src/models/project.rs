@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::APP_STATE;
 
-use super::app::App;
+use super::{app::App, manifest::Manifest};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Project {
@@ -27,6 +27,17 @@ impl Project {
         }
     }
 
+    pub fn from_cli_apply(args: &ArgMatches) -> Self {
+        let file = args.get_one::<String>("file");
+
+        let Some(file) = file else {
+            eprintln!("No manifest file provided");
+            std::process::exit(1);
+        };
+
+        Manifest::from_file(file).into_project()
+    }
+
     pub fn from_cli_start(args: &ArgMatches) -> Self {
         let name = args.get_one::<String>("project");
 
@@ -0,0 +1,99 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// How nginx proxies requests to an app. Unset options use nginx's defaults.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ProxyOptions {
+    /// Maximum size of a request body, e.g. `50m`. nginx defaults to `1m`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_body_size: Option<String>,
+    /// Timeout between two reads from the app, e.g. `300s`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_timeout: Option<String>,
+    /// Timeout between two writes to the app, e.g. `300s`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_timeout: Option<String>,
+    /// Whether nginx buffers responses of the app. Turn it off for streaming
+    /// responses such as server-sent events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buffering: Option<bool>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub websockets: bool,
+    /// Headers sent to the app, overriding the ones nginx sets by default.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub request_headers: IndexMap<String, String>,
+    /// Headers added to every response.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub response_headers: IndexMap<String, String>,
+    /// Origins allowed to make cross-origin requests, or `*` for any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cors_origins: Vec<String>,
+}
+
+impl ProxyOptions {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Applies one `KEY[=VALUE]` option from the command line, e.g.
+    /// `max_body_size=50m`, `websockets` or `response_header=X-Frame-Options:DENY`.
+    pub fn set(&mut self, option: &str) -> Result<(), String> {
+        let (key, value) = match option.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (option, None),
+        };
+
+        let required = || value.ok_or(format!("Proxy option {} requires a value", key));
+        let header = || {
+            let value = required()?;
+            match value.split_once(':') {
+                Some((name, value)) if !name.trim().is_empty() => {
+                    Ok((name.trim().to_owned(), value.trim().to_owned()))
+                }
+                _ => Err(format!("Header {} must be written as NAME:VALUE", value)),
+            }
+        };
+
+        match key {
+            "max_body_size" => self.max_body_size = Some(Self::size(required()?)?),
+            "read_timeout" => self.read_timeout = Some(Self::duration(required()?)?),
+            "send_timeout" => self.send_timeout = Some(Self::duration(required()?)?),
+            "buffering" => match value {
+                None | Some("on") => self.buffering = Some(true),
+                Some("off") => self.buffering = Some(false),
+                Some(value) => return Err(format!("Invalid buffering {}, use on or off", value)),
+            },
+            "websockets" => self.websockets = true,
+            "request_header" => {
+                let (name, value) = header()?;
+                self.request_headers.insert(name, value);
+            }
+            "response_header" => {
+                let (name, value) = header()?;
+                self.response_headers.insert(name, value);
+            }
+            "cors" => self.cors_origins.push(required()?.to_owned()),
+            _ => return Err(format!("Unknown proxy option {}", key)),
+        }
+        Ok(())
+    }
+
+    /// Validates an nginx size such as `512k` or `50m`.
+    fn size(value: &str) -> Result<String, String> {
+        Self::number_with_unit(value, &["", "k", "K", "m", "M", "g", "G"])
+            .ok_or(format!("Invalid size {}", value))
+    }
+
+    /// Validates an nginx duration such as `30s` or `5m`.
+    fn duration(value: &str) -> Result<String, String> {
+        Self::number_with_unit(value, &["", "ms", "s", "m", "h", "d"])
+            .ok_or(format!("Invalid duration {}", value))
+    }
+
+    fn number_with_unit(value: &str, units: &[&str]) -> Option<String> {
+        let digits = value.chars().take_while(char::is_ascii_digit).count();
+        let valid = digits > 0 && units.contains(&&value[digits..]);
+        valid.then(|| value.to_owned())
+    }
+}
@@ -0,0 +1,183 @@
+use std::{fmt, net::IpAddr};
+
+use serde::{Deserialize, Serialize};
+
+use super::stream::Protocol;
+
+/// A single port or an inclusive range of ports, e.g. `5432` or `8000-8010`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+/// A port of the app's container published directly on the host, written
+/// the way Docker does: `[IP:]HOST_PORT:CONTAINER_PORT[/tcp|/udp]`, e.g.
+/// `127.0.0.1:5433:5432/tcp` or `27015-27020:27015-27020/udp`. The traffic
+/// does not pass through nginx.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct PortMapping {
+    /// Address of the host to listen on. All addresses if unset.
+    pub host_ip: Option<IpAddr>,
+    pub host_ports: PortRange,
+    pub container_ports: PortRange,
+    pub protocol: Protocol,
+}
+
+impl PortRange {
+    fn parse(value: &str) -> Option<Self> {
+        let parse_port = |port: &str| port.parse::<u16>().ok().filter(|port| *port != 0);
+        let (start, end) = match value.split_once('-') {
+            Some((start, end)) => (parse_port(start)?, parse_port(end)?),
+            None => (parse_port(value)?, parse_port(value)?),
+        };
+        (start <= end).then_some(Self { start, end })
+    }
+
+    fn len(&self) -> u16 {
+        self.end - self.start + 1
+    }
+
+    fn overlaps(&self, other: &PortRange) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.start == self.end {
+            true => write!(f, "{}", self.start),
+            false => write!(f, "{}-{}", self.start, self.end),
+        }
+    }
+}
+
+impl PortMapping {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (mapping, protocol) = match spec.rsplit_once('/') {
+            Some((mapping, "tcp")) => (mapping, Protocol::Tcp),
+            Some((mapping, "udp")) => (mapping, Protocol::Udp),
+            Some((_, protocol)) => {
+                return Err(format!("Unknown protocol {} in port {}", protocol, spec))
+            }
+            None => (spec, Protocol::Tcp),
+        };
+
+        // IPv6 addresses are written in brackets, as they contain colons
+        let (host_ip, ports) = match mapping.strip_prefix('[') {
+            Some(rest) => match rest.split_once("]:") {
+                Some((ip, ports)) => (Some(ip), ports),
+                None => return Err(Self::invalid(spec)),
+            },
+            None => match mapping.matches(':').count() {
+                2 => {
+                    let (ip, ports) = mapping.split_once(':').unwrap();
+                    (Some(ip), ports)
+                }
+                _ => (None, mapping),
+            },
+        };
+        let host_ip = match host_ip {
+            Some(ip) => match ip.parse::<IpAddr>() {
+                Ok(ip) => Some(ip),
+                Err(_) => return Err(format!("Invalid address {} in port {}", ip, spec)),
+            },
+            None => None,
+        };
+
+        let Some((host_ports, container_ports)) = ports.split_once(':') else {
+            return Err(Self::invalid(spec));
+        };
+        let parse_range = |range: &str| {
+            PortRange::parse(range).ok_or(format!("Invalid port {} in port {}", range, spec))
+        };
+        let host_ports = parse_range(host_ports)?;
+        let container_ports = parse_range(container_ports)?;
+        if host_ports.len() != container_ports.len() {
+            return Err(format!(
+                "Port ranges of {} must have the same length on the host and in the container",
+                spec
+            ));
+        }
+
+        Ok(Self {
+            host_ip,
+            host_ports,
+            container_ports,
+            protocol,
+        })
+    }
+
+    fn invalid(spec: &str) -> String {
+        format!(
+            "Invalid port {}, use [IP:]HOST_PORT:CONTAINER_PORT[/tcp|/udp], e.g. 127.0.0.1:5433:5432",
+            spec
+        )
+    }
+
+    /// The host port of every container port, with the key docker uses for
+    /// the container port, e.g. `("5432/tcp", 5433)`.
+    pub fn bindings(&self) -> Vec<(String, u16)> {
+        (0..self.container_ports.len())
+            .map(|offset| {
+                let container_port = self.container_ports.start + offset;
+                let key = format!("{}/{}", container_port, self.protocol.name());
+                (key, self.host_ports.start + offset)
+            })
+            .collect()
+    }
+
+    /// True if the mapping needs `port` on any address of the host, e.g.
+    /// one nginx listens on.
+    pub fn takes(&self, port: u16, protocol: Protocol) -> bool {
+        self.protocol == protocol && self.host_ports.start <= port && port <= self.host_ports.end
+    }
+
+    /// True if both mappings need the same host port on the same address.
+    pub fn overlaps(&self, other: &PortMapping) -> bool {
+        self.protocol == other.protocol
+            && self.host_ports.overlaps(&other.host_ports)
+            && Self::same_address(self.host_ip, other.host_ip)
+    }
+
+    fn same_address(ip: Option<IpAddr>, other_ip: Option<IpAddr>) -> bool {
+        match (ip, other_ip) {
+            (Some(ip), Some(other_ip)) => {
+                ip == other_ip || ip.is_unspecified() || other_ip.is_unspecified()
+            }
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for PortMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.host_ip {
+            Some(IpAddr::V6(ip)) => write!(f, "[{}]:", ip)?,
+            Some(IpAddr::V4(ip)) => write!(f, "{}:", ip)?,
+            None => {}
+        }
+        write!(
+            f,
+            "{}:{}/{}",
+            self.host_ports,
+            self.container_ports,
+            self.protocol.name()
+        )
+    }
+}
+
+impl TryFrom<String> for PortMapping {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl From<PortMapping> for String {
+    fn from(mapping: PortMapping) -> Self {
+        mapping.to_string()
+    }
+}
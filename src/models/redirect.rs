@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+/// A domain that only redirects to another host, keeping the path and query,
+/// e.g. `www.example.com` to `example.com`. The certificate of the app covers
+/// the redirecting domain as well.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Redirect {
+    pub from: String,
+    pub to: String,
+    #[serde(default = "Redirect::default_status")]
+    pub status: u16,
+}
+
+impl Redirect {
+    const STATUSES: [u16; 5] = [301, 302, 303, 307, 308];
+
+    fn default_status() -> u16 {
+        301
+    }
+
+    /// True if a `--domain` value is a redirect rather than a served domain.
+    pub fn is_redirect(spec: &str) -> bool {
+        spec.contains("->")
+    }
+
+    /// Parses `FROM->TO[,STATUS]`, e.g. `www.example.com->example.com,308`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (hosts, status) = match spec.split_once(',') {
+            Some((hosts, status)) => {
+                let status = status
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid status {} in redirect {}", status, spec))?;
+                (hosts, status)
+            }
+            None => (spec, Self::default_status()),
+        };
+
+        let Some((from, to)) = hosts.split_once("->") else {
+            return Err(format!("Redirect {} must be written as FROM->TO", spec));
+        };
+
+        let redirect = Self {
+            from: from.trim().to_owned(),
+            to: to.trim().to_owned(),
+            status,
+        };
+        redirect.validate()?;
+        Ok(redirect)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.from.is_empty() || self.to.is_empty() {
+            return Err(format!(
+                "Redirect {} -> {} is missing a host",
+                self.from, self.to
+            ));
+        }
+        if !Self::STATUSES.contains(&self.status) {
+            return Err(format!(
+                "Invalid redirect status {}, use one of 301, 302, 303, 307 or 308",
+                self.status
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// When Docker restarts the containers of an app, written the way Docker
+/// does: `no`, `on-failure[:MAX_RETRIES]`, `unless-stopped` or `always`.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum RestartPolicy {
+    No,
+    /// Restart after the app exits with an error, at most `max_retries`
+    /// times in a row if set.
+    OnFailure {
+        max_retries: Option<u32>,
+    },
+    /// Restart unless the container was stopped, also after a reboot.
+    UnlessStopped,
+    Always,
+}
+
+impl RestartPolicy {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.split_once(':') {
+            Some(("on-failure", max_retries)) => match max_retries.parse() {
+                Ok(max_retries) => Ok(Self::OnFailure {
+                    max_retries: Some(max_retries),
+                }),
+                Err(_) => Err(format!("Invalid maximum retry count {}", max_retries)),
+            },
+            None => match value {
+                "no" => Ok(Self::No),
+                "on-failure" => Ok(Self::OnFailure { max_retries: None }),
+                "unless-stopped" => Ok(Self::UnlessStopped),
+                "always" => Ok(Self::Always),
+                _ => Err(Self::invalid(value)),
+            },
+            Some(_) => Err(Self::invalid(value)),
+        }
+    }
+
+    fn invalid(value: &str) -> String {
+        format!(
+            "Invalid restart policy {}, use no, on-failure[:MAX_RETRIES], unless-stopped or always",
+            value
+        )
+    }
+}
+
+impl fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::No => write!(f, "no"),
+            Self::OnFailure { max_retries: None } => write!(f, "on-failure"),
+            Self::OnFailure {
+                max_retries: Some(max_retries),
+            } => write!(f, "on-failure:{}", max_retries),
+            Self::UnlessStopped => write!(f, "unless-stopped"),
+            Self::Always => write!(f, "always"),
+        }
+    }
+}
+
+impl TryFrom<String> for RestartPolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl From<RestartPolicy> for String {
+    fn from(policy: RestartPolicy) -> Self {
+        policy.to_string()
+    }
+}
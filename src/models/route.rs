@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// Mounts an app under a path of a domain. All routes for a domain are merged
+/// into one nginx server block, whichever app or project declares them.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    pub domain: String,
+    #[serde(default = "Route::default_path")]
+    pub path: String,
+    /// Port of the app that serves the route. Defaults to the app's port.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    /// Removes `path` from the request URI before it is passed to the app.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strip_prefix: bool,
+}
+
+impl Route {
+    fn default_path() -> String {
+        "/".to_owned()
+    }
+
+    /// Parses `DOMAIN[/PATH][,port=PORT][,strip_prefix]`, e.g.
+    /// `example.com/api,port=8000,strip_prefix`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec.split(',');
+        let target = parts.next().unwrap_or_default();
+
+        let (domain, path) = match target.find('/') {
+            Some(index) => (&target[..index], &target[index..]),
+            None => (target, "/"),
+        };
+        if domain.is_empty() {
+            return Err(format!("Route {} has no domain", spec));
+        }
+
+        let mut route = Route {
+            domain: domain.to_owned(),
+            path: Self::normalize_path(path),
+            port: None,
+            strip_prefix: false,
+        };
+
+        for option in parts {
+            match option.split_once('=') {
+                Some(("port", port)) => route.port = Some(port.to_owned()),
+                None if option == "strip_prefix" => route.strip_prefix = true,
+                _ => return Err(format!("Unknown route option {} in {}", option, spec)),
+            }
+        }
+
+        Ok(route)
+    }
+
+    /// Paths always start with a slash and never end with one, except for
+    /// the root path.
+    pub fn normalize_path(path: &str) -> String {
+        let path = path.trim_matches('/');
+        format!("/{}", path)
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+        }
+    }
+}
+
+/// A raw TCP or UDP listener of the nginx container, forwarded to an app.
+/// The port is published on the host when the nginx container is created.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Stream {
+    /// Port nginx listens on, and that is published on the host.
+    pub port: u16,
+    /// Port of the app that receives the traffic. Defaults to `port`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Stream::is_tcp")]
+    pub protocol: Protocol,
+}
+
+impl Stream {
+    fn is_tcp(protocol: &Protocol) -> bool {
+        *protocol == Protocol::Tcp
+    }
+
+    /// Parses `PORT[:TARGET_PORT][/tcp|/udp]`, e.g. `5432` or `27015/udp`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (ports, protocol) = match spec.split_once('/') {
+            Some((ports, "tcp")) => (ports, Protocol::Tcp),
+            Some((ports, "udp")) => (ports, Protocol::Udp),
+            Some((_, protocol)) => {
+                return Err(format!("Unknown protocol {} in stream {}", protocol, spec))
+            }
+            None => (spec, Protocol::Tcp),
+        };
+
+        let parse_port = |port: &str| {
+            port.parse::<u16>()
+                .ok()
+                .filter(|port| *port != 0)
+                .ok_or(format!("Invalid port {} in stream {}", port, spec))
+        };
+
+        let (port, target_port) = match ports.split_once(':') {
+            Some((port, target_port)) => (parse_port(port)?, Some(parse_port(target_port)?)),
+            None => (parse_port(ports)?, None),
+        };
+
+        Ok(Self {
+            port,
+            target_port,
+            protocol,
+        })
+    }
+
+    /// The key docker uses for the port, e.g. `5432/tcp`.
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.port, self.protocol.name())
+    }
+
+    pub fn target_port(&self) -> u16 {
+        self.target_port.unwrap_or(self.port)
+    }
+}
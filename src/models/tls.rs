@@ -0,0 +1,57 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// How the certificate for an app's domains is obtained.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TlsMode {
+    /// Requested from Let's Encrypt with certbot.
+    Letsencrypt,
+    /// Self-signed certificate generated with OpenSSL.
+    Openssl,
+    /// Certificate and key files provided by the user.
+    Custom,
+    /// No TLS. The app is served over plain HTTP.
+    None,
+}
+
+impl TlsMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "letsencrypt" => Some(Self::Letsencrypt),
+            "openssl" => Some(Self::Openssl),
+            "custom" => Some(Self::Custom),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Letsencrypt => "letsencrypt",
+            Self::Openssl => "openssl",
+            Self::Custom => "custom",
+            Self::None => "none",
+        }
+    }
+
+    /// Reads the TLS mode of an app from the config file. Older versions
+    /// stored `openssl: bool` instead, which maps to openssl or letsencrypt.
+    pub fn deserialize_legacy<'de, D>(deserializer: D) -> Result<Option<Self>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Mode(TlsMode),
+            OpenSsl(bool),
+        }
+
+        Ok(match Option::<Stored>::deserialize(deserializer)? {
+            Some(Stored::Mode(mode)) => Some(mode),
+            Some(Stored::OpenSsl(true)) => Some(Self::Openssl),
+            Some(Stored::OpenSsl(false)) => Some(Self::Letsencrypt),
+            None => None,
+        })
+    }
+}
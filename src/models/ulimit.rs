@@ -0,0 +1,78 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A resource limit of the processes in a container, written the way Docker
+/// does: `NAME=SOFT[:HARD]`, e.g. `nofile=1024:2048`. -1 means unlimited.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Ulimit {
+    pub name: String,
+    pub soft: i64,
+    /// The same as `soft` if unset.
+    pub hard: Option<i64>,
+}
+
+impl Ulimit {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Invalid ulimit {}, use NAME=SOFT[:HARD], e.g. nofile=1024:2048",
+                spec
+            )
+        };
+
+        let Some((name, limits)) = spec.split_once('=') else {
+            return Err(invalid());
+        };
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase()) {
+            return Err(invalid());
+        }
+
+        let parse_limit = |limit: &str| limit.trim().parse::<i64>().map_err(|_| invalid());
+        let (soft, hard) = match limits.split_once(':') {
+            Some((soft, hard)) => (parse_limit(soft)?, Some(parse_limit(hard)?)),
+            None => (parse_limit(limits)?, None),
+        };
+        if hard.is_some_and(|hard| hard != -1 && (soft == -1 || soft > hard)) {
+            return Err(format!(
+                "Soft limit of ulimit {} exceeds its hard limit",
+                spec
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_owned(),
+            soft,
+            hard,
+        })
+    }
+
+    pub fn hard(&self) -> i64 {
+        self.hard.unwrap_or(self.soft)
+    }
+}
+
+impl fmt::Display for Ulimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.hard {
+            Some(hard) => write!(f, "{}={}:{}", self.name, self.soft, hard),
+            None => write!(f, "{}={}", self.name, self.soft),
+        }
+    }
+}
+
+impl TryFrom<String> for Ulimit {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl From<Ulimit> for String {
+    fn from(ulimit: Ulimit) -> Self {
+        ulimit.to_string()
+    }
+}
@@ -0,0 +1,88 @@
+/// A directive of an nginx config file, either simple (`name args;`) or a
+/// block (`name args { ... }`). Arguments are quoted when rendered, so values
+/// such as domains or paths can never break out of their directive.
+#[derive(Clone)]
+pub enum Directive {
+    Simple {
+        name: &'static str,
+        args: Vec<String>,
+    },
+    Block {
+        name: &'static str,
+        args: Vec<String>,
+        directives: Vec<Directive>,
+    },
+}
+
+impl Directive {
+    pub fn simple(name: &'static str, args: &[&str]) -> Self {
+        Directive::Simple {
+            name,
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    pub fn block(name: &'static str, args: &[&str], directives: Vec<Directive>) -> Self {
+        Directive::Block {
+            name,
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            directives,
+        }
+    }
+
+    /// Renders a list of top level directives as the content of a file.
+    pub fn render_all(directives: &[Directive]) -> String {
+        let mut conf = String::new();
+        Self::render_list(directives, 0, &mut conf);
+        conf
+    }
+
+    fn render_list(directives: &[Directive], depth: usize, conf: &mut String) {
+        for (index, directive) in directives.iter().enumerate() {
+            // Blocks are separated from their neighbours by an empty line
+            let is_block = matches!(directive, Directive::Block { .. });
+            let follows_block =
+                index > 0 && matches!(directives[index - 1], Directive::Block { .. });
+            if index > 0 && (is_block || follows_block) {
+                conf.push('\n');
+            }
+            directive.render(depth, conf);
+        }
+    }
+
+    fn render(&self, depth: usize, conf: &mut String) {
+        let indent = "    ".repeat(depth);
+        match self {
+            Directive::Simple { name, args } => {
+                conf.push_str(&format!("{indent}{name}{};\n", Self::render_args(args)));
+            }
+            Directive::Block {
+                name,
+                args,
+                directives,
+            } => {
+                conf.push_str(&format!("{indent}{name}{} {{\n", Self::render_args(args)));
+                Self::render_list(directives, depth + 1, conf);
+                conf.push_str(&format!("{indent}}}\n"));
+            }
+        }
+    }
+
+    fn render_args(args: &[String]) -> String {
+        args.iter()
+            .map(|arg| format!(" {}", Self::quote(arg)))
+            .collect()
+    }
+
+    /// Quotes an argument if it contains characters that nginx would treat
+    /// as syntax.
+    fn quote(arg: &str) -> String {
+        let special = |c: char| c.is_whitespace() || "\"';{}#\\".contains(c);
+        if !arg.is_empty() && !arg.contains(special) {
+            return arg.to_owned();
+        }
+
+        let escaped = arg.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("\"{}\"", escaped)
+    }
+}
@@ -1,9 +1,10 @@
 #!/bin/sh
 
 function inotifywait_listen() {
-    while inotifywait -e create -e modify -e delete -e move /etc/nginx/conf.d; do
+    while inotifywait -e create -e modify -e delete -e move /etc/nginx/conf.d /etc/nginx/stream.d; do
         echo "Configuration change detected, reloading Nginx..."
-        nginx -s reload
+        # nbot restores the previous file if the new config is invalid
+        nginx -t && nginx -s reload
     done
 }
 
@@ -18,7 +19,7 @@ crontab -l
 # Start the cron daemon
 crond
 
-# Monitor the conf.d directory for changes and reload Nginx when a change is detected
+# Monitor the conf.d and stream.d directories for changes and reload Nginx when a change is detected
 inotifywait_listen &
 
 # Start Nginx
@@ -1,52 +1,4 @@
 
-add_conf() {
-    # 1. file name
-    # 2. container name
-    # 3. port
-    # 4..n. domains
-
-    # Ensure the NAME and VALUE variables are passed
-    if [ -z "$1" ] || [ -z "$2" ] || [ -z "$3" ] || [ -z "$4" ]; then
-        >&2 echo "Usage: add_conf FILE_NAME CONTAINER_NAME PORT DOMAIN [...MORE_DOMAINS]"
-        exit 1
-    fi
-
-    FILE_NAME="$1"
-    CONTAINER_NAME="$2"
-    PORT="$3"
-    DOMAIN="$4"
-    DESTINATION="/etc/nginx/conf.d/${FILE_NAME}.conf"
-
-    # POSIX compliant way to shift the arguments
-    shift 4
-    DOMAINS="${DOMAIN} "
-    while [ "$#" -gt 0 ]; do
-        DOMAINS="$DOMAINS$1 "
-        shift
-    done
-
-    # Check if the template file exists
-    if [[ ! -f "/template.conf" ]]; then
-        >&2 echo "The template file /template.conf does not exist."
-        exit 1
-    fi
-
-    # Copying the template file to the destination
-    cp /template.conf /temp.conf
-
-    # Replacing the placeholders with the provided values
-    sed -i "s/{{port}}/${PORT}/g" /temp.conf
-    sed -i "s/{{domain}}/${DOMAIN}/g" /temp.conf
-    sed -i "s/{{domains}}/${DOMAINS}/g" /temp.conf
-    sed -i "s/{{container_name}}/${CONTAINER_NAME}/g" /temp.conf
-
-    # Move the file to the destination
-    mv /temp.conf ${DESTINATION}
-
-    echo "File copied and value replaced successfully."
-    # The Nginx service reloads automatically when the folder is modified due to inotifywait
-}
-
 remove_conf() {
     # 1. file name
 
@@ -102,30 +54,43 @@ generate_certs_certbot() {
 }
 
 generate_certs_openssl() {
-    # 1. domain
-    
+    # 1..n. domains, the first one names the certificate
+
     if [ -z "$1" ]; then
-        >&2 echo "Usage: generate_certificate_openssl DOMAIN"
+        >&2 echo "Usage: generate_certificate_openssl ...DOMAINS"
         exit 1
     fi
 
     DOMAIN="$1"
 
+    SAN=""
+    for NAME in "$@"; do
+        SAN="${SAN:+${SAN},}DNS:${NAME}"
+    done
+
     mkdir -p /etc/letsencrypt/live/${DOMAIN}
 
     OUT="/etc/letsencrypt/live/${DOMAIN}/fullchain.pem"
     KEYOUT="/etc/letsencrypt/live/${DOMAIN}/privkey.pem"
     SUBJECT="/C=''/ST=''/L=''/O=''/OU=''/CN=${DOMAIN}"
 
+    # Keep a valid certificate only if it covers every domain
     openssl x509 -checkend 86400 -noout -in ${OUT}
     EXIST=$?
     if [ $EXIST -eq 0 ]; then
-        echo "Certificate is still valid"
-        exit 0
+        NAMES=$(openssl x509 -noout -ext subjectAltName -in ${OUT} 2>/dev/null)
+        COVERED=0
+        for NAME in "$@"; do
+            echo "${NAMES}" | grep -qE "DNS:${NAME}(,|\$)" || COVERED=1
+        done
+        if [ $COVERED -eq 0 ]; then
+            echo "Certificate is still valid"
+            exit 0
+        fi
     fi
 
     # Generate the SSL certificate using OpenSSL
-    openssl req -x509 -nodes -days 365 -newkey rsa:2048 -keyout ${KEYOUT} -out ${OUT} -subj "${SUBJECT}"
+    openssl req -x509 -nodes -days 365 -newkey rsa:2048 -keyout ${KEYOUT} -out ${OUT} -subj "${SUBJECT}" -addext "subjectAltName=${SAN}"
 }
 
 remove_certs() {
@@ -0,0 +1,52 @@
+use crate::{configs::app_state::AppState, models::App};
+
+use super::conf::Directive;
+
+/// The shared memory zones of the rate and connection limits of every app
+/// that serves a domain, across all projects. A zone belongs to the app, so a
+/// client shares one limit across all domains of the app.
+pub struct Limits {
+    zones: Vec<Directive>,
+}
+
+impl Limits {
+    pub fn from_state(state: &AppState) -> Self {
+        let mut zones = vec![];
+        for app in state.apps().filter(|app| app.is_public()) {
+            for (index, limit) in app.rate_limits.iter().enumerate() {
+                let definition = format!("zone={}:10m", Self::rate_zone(app, index));
+                let rate = format!("rate={}", limit.rate);
+                zones.push(Directive::simple(
+                    "limit_req_zone",
+                    &["$binary_remote_addr", &definition, &rate],
+                ));
+            }
+            if app.conn_limit.is_some() {
+                let definition = format!("zone={}:10m", Self::conn_zone(app));
+                zones.push(Directive::simple(
+                    "limit_conn_zone",
+                    &["$binary_remote_addr", &definition],
+                ));
+            }
+        }
+
+        Self { zones }
+    }
+
+    /// Zone of the rate limit at `index` in the limits of `app`.
+    pub fn rate_zone(app: &App, index: usize) -> String {
+        format!("{}_{}", app.container_name, index)
+    }
+
+    pub fn conn_zone(app: &App) -> String {
+        format!("{}_conn", app.container_name)
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        "nbot_limits.conf"
+    }
+
+    pub fn render(&self) -> String {
+        Directive::render_all(&self.zones)
+    }
+}
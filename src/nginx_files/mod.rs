@@ -1,6 +1,15 @@
+mod conf;
+mod limits;
+mod site;
+mod streams;
+
+pub use limits::Limits;
+pub use site::Site;
+pub use streams::Streams;
+
 pub static NGINX_DOCKERFILE: &str = include_str!("./Dockerfile");
 pub static NGINX_ENTRYPOINT: &str = include_str!("./entrypoint.sh");
 pub static NGINX_DEFAULT_CONF: &str = include_str!("./default.conf");
-pub static NGINX_TEMPLATE_CONF: &str = include_str!("./template.conf");
 pub static NGINX_SCHEDULER: &str = include_str!("./scheduler.txt");
 pub static NGINX_FUNCTIONS: &str = include_str!("./functions.sh");
+pub static NGINX_MAINTENANCE_PAGE: &str = include_str!("./maintenance.html");
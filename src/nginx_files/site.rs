@@ -0,0 +1,456 @@
+use crate::{
+    configs::{app_state::AppState, htpasswd::Htpasswd, maintenance::Maintenance},
+    models::{Balance, ProxyOptions, RateLimit, Redirect, TlsMode},
+};
+
+use super::{conf::Directive, limits::Limits};
+
+#[derive(Clone)]
+struct Location {
+    path: String,
+    /// Path of the route the location belongs to, which is stripped if the
+    /// route has `strip_prefix`.
+    route_path: String,
+    container_name: String,
+    /// Address requests are passed to, the container itself or the upstream
+    /// of its replicas.
+    upstream: String,
+    strip_prefix: bool,
+    proxy: ProxyOptions,
+    allow: Vec<String>,
+    deny: Vec<String>,
+    htpasswd: Option<String>,
+    rate_limits: Vec<(String, RateLimit)>,
+    conn_limit: Option<(String, u32)>,
+    /// Answer with the maintenance page instead of passing requests on.
+    maintenance: bool,
+}
+
+/// The nginx server block for one domain, merging the routes of every app
+/// that serves the domain, across all projects. A redirecting domain has no
+/// locations and only returns the redirect.
+pub struct Site {
+    pub domain: String,
+    cert_name: String,
+    tls: TlsMode,
+    redirect: Option<Redirect>,
+    /// Upstreams of the apps with more than one replica. Every site defines
+    /// its own, so no file depends on another.
+    upstreams: Vec<Directive>,
+    locations: Vec<Location>,
+}
+
+impl Site {
+    /// Collects everything served on `domain`. Returns `None` if no app
+    /// serves the domain anymore.
+    pub fn from_state(domain: &str, state: &AppState) -> Option<Self> {
+        let owner = state.domain_owner(domain)?;
+        let cert_name = state.owned_domains(owner).first()?.to_owned();
+
+        // Every route path on the domain, to keep limits of one app from
+        // taking over paths that another app is mounted on
+        let mut route_paths: Vec<(String, String)> = vec![];
+        for app in state.apps() {
+            for route in app.routes_for(domain) {
+                route_paths.push((app.container_name.to_owned(), route.path));
+            }
+        }
+
+        let mut upstreams = vec![];
+        let mut upstream_names: Vec<String> = vec![];
+        let mut locations = vec![];
+        for app in state.apps() {
+            let routes = app.routes_for(domain);
+            if routes.is_empty() {
+                continue;
+            }
+
+            // The zones are defined once for all sites, see `Limits`
+            let rate_limits: Vec<(String, RateLimit)> = app
+                .rate_limits
+                .iter()
+                .enumerate()
+                .map(|(index, limit)| (Limits::rate_zone(app, index), limit.to_owned()))
+                .collect();
+            let conn_limit = app.conn_limit.map(|limit| (Limits::conn_zone(app), limit));
+
+            for route in routes {
+                let port = route
+                    .port
+                    .or(app.port.to_owned())
+                    .unwrap_or("80".to_owned());
+                let hosts = state.upstream_hosts(app);
+                let upstream = match hosts.as_slice() {
+                    [host] => format!("http://{}:{}", host, port),
+                    _ => {
+                        let name = format!("{}_{}_{}", domain, app.container_name, port);
+                        if !upstream_names.contains(&name) {
+                            upstreams.push(Self::upstream(&name, app.balance, &hosts, &port));
+                            upstream_names.push(name.to_owned());
+                        }
+                        format!("http://{}", name)
+                    }
+                };
+                let location = Location {
+                    path: route.path.to_owned(),
+                    route_path: route.path.to_owned(),
+                    container_name: app.container_name.to_owned(),
+                    upstream,
+                    strip_prefix: route.strip_prefix,
+                    proxy: app.proxy.to_owned(),
+                    allow: app.allow.to_owned(),
+                    deny: app.deny.to_owned(),
+                    htpasswd: Htpasswd::exists(&app.container_name)
+                        .then(|| Htpasswd::nginx_path(&app.container_name)),
+                    rate_limits: vec![],
+                    conn_limit: conn_limit.to_owned(),
+                    maintenance: Maintenance::is_on(&app.container_name),
+                };
+
+                // Limits without a path apply to every location of the app,
+                // limits with one get a location of their own
+                let app_wide: Vec<(String, RateLimit)> = rate_limits
+                    .iter()
+                    .filter(|(_, limit)| limit.path.is_none())
+                    .cloned()
+                    .collect();
+                let mut limited_paths: Vec<String> = vec![];
+                for (_, limit) in &rate_limits {
+                    let Some(path) = &limit.path else {
+                        continue;
+                    };
+                    let shadowed = route_paths.iter().any(|(container_name, other_path)| {
+                        container_name != &app.container_name
+                            && other_path.len() > route.path.len()
+                            && Self::is_below(path, other_path)
+                    });
+                    if Self::is_below(path, &route.path)
+                        && !shadowed
+                        && !limited_paths.contains(&path.to_owned())
+                    {
+                        limited_paths.push(path.to_owned());
+                    }
+                }
+
+                for path in limited_paths {
+                    let mut limits = app_wide.clone();
+                    limits.extend(
+                        rate_limits
+                            .iter()
+                            .filter(|(_, limit)| limit.path.as_ref() == Some(&path))
+                            .cloned(),
+                    );
+                    if path == route.path {
+                        locations.push(Location {
+                            rate_limits: limits,
+                            ..location.clone()
+                        });
+                    } else {
+                        locations.push(Location {
+                            path,
+                            rate_limits: limits,
+                            ..location.clone()
+                        });
+                    }
+                }
+
+                if !locations.iter().any(|l| l.path == route.path) {
+                    locations.push(Location {
+                        rate_limits: app_wide,
+                        ..location
+                    });
+                }
+            }
+        }
+
+        // nginx picks the longest matching prefix, sorting only keeps the
+        // generated file stable
+        locations.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let redirect = state
+            .apps()
+            .find_map(|app| app.redirect_for(domain))
+            .cloned();
+
+        Some(Self {
+            domain: domain.to_owned(),
+            cert_name,
+            tls: owner.tls.unwrap_or(TlsMode::Letsencrypt),
+            redirect,
+            upstreams,
+            locations,
+        })
+    }
+
+    /// Balances requests over the replicas of an app. Replicas are resolved
+    /// at runtime, so nginx accepts the config while some are not running.
+    fn upstream(name: &str, balance: Option<Balance>, hosts: &[String], port: &str) -> Directive {
+        let mut directives = vec![Directive::simple("zone", &[name, "64k"])];
+        match balance.unwrap_or_default() {
+            Balance::RoundRobin => {}
+            Balance::LeastConn => directives.push(Directive::simple("least_conn", &[])),
+            Balance::IpHash => directives.push(Directive::simple("ip_hash", &[])),
+        }
+        directives.push(Directive::simple("resolver", &["127.0.0.11", "valid=30s"]));
+        for host in hosts {
+            let server = format!("{}:{}", host, port);
+            directives.push(Directive::simple("server", &[&server, "resolve"]));
+        }
+        Directive::block("upstream", &[name], directives)
+    }
+
+    /// True if requests for `path` are matched by a location for `prefix`.
+    fn is_below(path: &str, prefix: &str) -> bool {
+        prefix == "/" || path == prefix || path.starts_with(&format!("{}/", prefix))
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("{}.conf", self.domain)
+    }
+
+    pub fn render(&self) -> String {
+        let mut directives = vec![];
+        match self.tls {
+            TlsMode::None => directives.push(Directive::simple("listen", &["80"])),
+            _ => {
+                directives.push(Directive::simple("listen", &["443", "ssl"]));
+                directives.push(Directive::simple("http2", &["on"]));
+            }
+        }
+        directives.push(Directive::simple("server_name", &[&self.domain]));
+        directives.push(Directive::simple("resolver", &["127.0.0.11", "valid=30s"]));
+
+        if self.tls != TlsMode::None {
+            let live = format!("/etc/letsencrypt/live/{}", self.cert_name);
+            let certificate = format!("{live}/fullchain.pem");
+            let key = format!("{live}/privkey.pem");
+            directives.extend([
+                Directive::simple("ssl_certificate", &[&certificate]),
+                Directive::simple("ssl_certificate_key", &[&key]),
+                Directive::simple("ssl_session_cache", &["shared:SSL:10m"]),
+                Directive::simple("ssl_session_timeout", &["10m"]),
+                Directive::simple("ssl_ciphers", &["HIGH:!aNULL:!MD5"]),
+                Directive::simple("ssl_protocols", &["TLSv1.2", "TLSv1.3"]),
+                Directive::simple("ssl_prefer_server_ciphers", &["on"]),
+            ]);
+        }
+
+        if let Some(redirect) = &self.redirect {
+            let status = redirect.status.to_string();
+            let target = format!("$scheme://{}$request_uri", redirect.to);
+            directives.push(Directive::simple("return", &[&status, &target]));
+        }
+
+        for location in &self.locations {
+            directives.extend(location.directives());
+        }
+
+        let mut maintenance: Vec<&str> = vec![];
+        for location in self.locations.iter().filter(|l| l.maintenance) {
+            if !maintenance.contains(&location.container_name.as_str()) {
+                maintenance.push(&location.container_name);
+            }
+        }
+        for container_name in maintenance {
+            directives.push(Location::maintenance_page(container_name));
+        }
+
+        let mut conf: Vec<Directive> = self.upstreams.to_vec();
+        conf.push(Directive::block("server", &[], directives));
+        Directive::render_all(&conf)
+    }
+}
+
+impl Location {
+    fn directives(&self) -> Vec<Directive> {
+        if self.path == "/" {
+            return vec![self.block(&["/"])];
+        }
+
+        // /api/ matches everything below the path, = /api the path itself
+        let nested = format!("{}/", self.path);
+        vec![self.block(&[&nested]), self.block(&["=", &self.path])]
+    }
+
+    fn block(&self, location: &[&str]) -> Directive {
+        if self.maintenance {
+            let page = format!("@{}_maintenance", self.container_name);
+            let directives = vec![
+                Directive::simple("error_page", &["503", &page]),
+                Directive::simple("return", &["503"]),
+            ];
+            return Directive::block("location", location, directives);
+        }
+
+        let proxy = &self.proxy;
+        let mut directives = vec![Directive::simple("set", &["$upstream", &self.upstream])];
+
+        if self.strip_prefix {
+            let pattern = format!("^{}/?(.*)$", Self::escape_regex(&self.route_path));
+            directives.push(Directive::simple("rewrite", &[&pattern, "/$1", "break"]));
+        }
+
+        // Rules are checked in order, so denied addresses win over ranges
+        // that are allowed
+        for address in &self.deny {
+            directives.push(Directive::simple("deny", &[address]));
+        }
+        for address in &self.allow {
+            directives.push(Directive::simple("allow", &[address]));
+        }
+        if !self.allow.is_empty() {
+            directives.push(Directive::simple("deny", &["all"]));
+        }
+
+        if let Some(htpasswd) = &self.htpasswd {
+            directives.push(Directive::simple("auth_basic", &["Restricted"]));
+            directives.push(Directive::simple("auth_basic_user_file", &[htpasswd]));
+        }
+
+        for (zone, limit) in &self.rate_limits {
+            let zone = format!("zone={}", zone);
+            let burst = limit.burst.map(|burst| format!("burst={}", burst));
+            let mut args = vec![zone.as_str()];
+            args.extend(burst.as_deref());
+            if limit.nodelay {
+                args.push("nodelay");
+            }
+            directives.push(Directive::simple("limit_req", &args));
+        }
+        if let Some((zone, limit)) = &self.conn_limit {
+            directives.push(Directive::simple("limit_conn", &[zone, &limit.to_string()]));
+        }
+        if !self.rate_limits.is_empty() || self.conn_limit.is_some() {
+            // 429 tells well-behaved clients to back off, unlike the default 503
+            directives.push(Directive::simple("limit_req_status", &["429"]));
+            directives.push(Directive::simple("limit_conn_status", &["429"]));
+        }
+
+        if let Some(size) = &proxy.max_body_size {
+            directives.push(Directive::simple("client_max_body_size", &[size]));
+        }
+        if let Some(timeout) = &proxy.read_timeout {
+            directives.push(Directive::simple("proxy_read_timeout", &[timeout]));
+        }
+        if let Some(timeout) = &proxy.send_timeout {
+            directives.push(Directive::simple("proxy_send_timeout", &[timeout]));
+        }
+        if let Some(buffering) = proxy.buffering {
+            let buffering = if buffering { "on" } else { "off" };
+            directives.push(Directive::simple("proxy_buffering", &[buffering]));
+        }
+
+        let mut request_headers = vec![
+            ("Host", "$host"),
+            ("X-Real-IP", "$remote_addr"),
+            ("X-Forwarded-For", "$proxy_add_x_forwarded_for"),
+            ("X-Forwarded-Proto", "$scheme"),
+        ];
+        if proxy.websockets {
+            directives.push(Directive::simple("proxy_http_version", &["1.1"]));
+            request_headers.push(("Upgrade", "$http_upgrade"));
+            request_headers.push(("Connection", "$http_connection"));
+        }
+        for (name, value) in &proxy.request_headers {
+            request_headers.retain(|(default, _)| !default.eq_ignore_ascii_case(name));
+            request_headers.push((name, value));
+        }
+        for (name, value) in request_headers {
+            directives.push(Directive::simple("proxy_set_header", &[name, value]));
+        }
+        directives.push(Directive::simple("proxy_pass", &["$upstream"]));
+
+        for (name, value) in &proxy.response_headers {
+            directives.push(Directive::simple("add_header", &[name, value, "always"]));
+        }
+        directives.extend(self.cors());
+
+        Directive::block("location", location, directives)
+    }
+
+    /// Serves the app's own maintenance page from the html volume, or the
+    /// default one, keeping the 503 status of the request.
+    fn maintenance_page(container_name: &str) -> Directive {
+        let name = format!("@{}_maintenance", container_name);
+        let page = Maintenance::page_path(container_name);
+        Directive::block(
+            "location",
+            &[&name],
+            vec![
+                Directive::simple("root", &["/usr/share/nginx/html"]),
+                Directive::simple("add_header", &["Cache-Control", "no-store", "always"]),
+                Directive::simple("add_header", &["Retry-After", "300", "always"]),
+                Directive::simple("try_files", &[&page, "/maintenance.html", "=503"]),
+            ],
+        )
+    }
+
+    /// Allows the configured origins to make cross-origin requests, and
+    /// answers preflight requests without passing them to the app.
+    fn cors(&self) -> Vec<Directive> {
+        let origins = &self.proxy.cors_origins;
+        if origins.is_empty() {
+            return vec![];
+        }
+
+        let mut directives = vec![];
+        if origins.iter().any(|origin| origin == "*") {
+            directives.push(Directive::simple(
+                "add_header",
+                &["Access-Control-Allow-Origin", "*", "always"],
+            ));
+        } else {
+            let escaped: Vec<String> = origins.iter().map(|o| Self::escape_regex(o)).collect();
+            let pattern = format!("^({})$", escaped.join("|"));
+            directives.extend([
+                Directive::simple("set", &["$cors_origin", ""]),
+                Directive::block(
+                    "if",
+                    &["(", "$http_origin", "~*", &pattern, ")"],
+                    vec![Directive::simple("set", &["$cors_origin", "$http_origin"])],
+                ),
+                Directive::simple(
+                    "add_header",
+                    &["Access-Control-Allow-Origin", "$cors_origin", "always"],
+                ),
+                Directive::simple("add_header", &["Vary", "Origin", "always"]),
+            ]);
+        }
+
+        directives.extend([
+            Directive::simple(
+                "add_header",
+                &[
+                    "Access-Control-Allow-Methods",
+                    "GET, POST, PUT, PATCH, DELETE, OPTIONS",
+                    "always",
+                ],
+            ),
+            Directive::simple(
+                "add_header",
+                &[
+                    "Access-Control-Allow-Headers",
+                    "$http_access_control_request_headers",
+                    "always",
+                ],
+            ),
+            Directive::block(
+                "if",
+                &["(", "$request_method", "=", "OPTIONS", ")"],
+                vec![Directive::simple("return", &["204"])],
+            ),
+        ]);
+        directives
+    }
+
+    fn escape_regex(path: &str) -> String {
+        let mut escaped = String::new();
+        for c in path.chars() {
+            if "\\.+*?()|[]{}^$".contains(c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+}
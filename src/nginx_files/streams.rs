@@ -0,0 +1,100 @@
+use crate::{
+    configs::app_state::AppState,
+    models::{Balance, Protocol},
+};
+
+use super::conf::Directive;
+
+struct Server {
+    port: u16,
+    protocol: Protocol,
+    /// The container itself, or the upstream of its replicas.
+    upstream: String,
+}
+
+/// The nginx stream config, with a server for each TCP/UDP stream of every
+/// app, across all projects.
+pub struct Streams {
+    upstreams: Vec<Directive>,
+    servers: Vec<Server>,
+}
+
+impl Streams {
+    pub fn from_state(state: &AppState) -> Self {
+        let mut upstreams = vec![];
+        let mut upstream_names: Vec<String> = vec![];
+        let mut servers = vec![];
+        for (app, stream) in state.streams() {
+            let target_port = stream.target_port();
+            let hosts = state.upstream_hosts(app);
+            let upstream = match hosts.as_slice() {
+                [host] => format!("{}:{}", host, target_port),
+                _ => {
+                    let name = format!("{}_{}", app.container_name, target_port);
+                    if !upstream_names.contains(&name) {
+                        upstreams.push(Self::upstream(&name, app.balance, &hosts, target_port));
+                        upstream_names.push(name.to_owned());
+                    }
+                    name
+                }
+            };
+            servers.push(Server {
+                port: stream.port,
+                protocol: stream.protocol,
+                upstream,
+            });
+        }
+
+        Self { upstreams, servers }
+    }
+
+    /// Balances connections over the replicas of an app, like the upstreams
+    /// of the HTTP sites.
+    fn upstream(name: &str, balance: Option<Balance>, hosts: &[String], port: u16) -> Directive {
+        let mut directives = vec![Directive::simple("zone", &[name, "64k"])];
+        match balance.unwrap_or_default() {
+            Balance::RoundRobin => {}
+            Balance::LeastConn => directives.push(Directive::simple("least_conn", &[])),
+            Balance::IpHash => {
+                directives.push(Directive::simple("hash", &["$remote_addr", "consistent"]))
+            }
+        }
+        directives.push(Directive::simple("resolver", &["127.0.0.11", "valid=30s"]));
+        for host in hosts {
+            let server = format!("{}:{}", host, port);
+            directives.push(Directive::simple("server", &[&server, "resolve"]));
+        }
+        Directive::block("upstream", &[name], directives)
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        "nbot.conf"
+    }
+
+    pub fn render(&self) -> String {
+        let mut conf = self.upstreams.to_vec();
+        conf.extend(self.servers.iter().map(Server::directive));
+        Directive::render_all(&conf)
+    }
+}
+
+impl Server {
+    fn directive(&self) -> Directive {
+        let port = self.port.to_string();
+        let listen = match self.protocol {
+            Protocol::Tcp => Directive::simple("listen", &[&port]),
+            Protocol::Udp => Directive::simple("listen", &[&port, "udp"]),
+        };
+
+        Directive::block(
+            "server",
+            &[],
+            vec![
+                listen,
+                Directive::simple("resolver", &["127.0.0.11", "valid=30s"]),
+                Directive::simple("set", &["$upstream", &self.upstream]),
+                Directive::simple("proxy_pass", &["$upstream"]),
+            ],
+        )
+    }
+}
@@ -1,7 +1,17 @@
 pub const NGINX_CERT_VOLUME: &str = "nbot_certs:/etc/letsencrypt:rw";
 pub const NGINX_CONFD_VOLUME: &str = "nbot_confd:/etc/nginx/conf.d";
+pub const NGINX_STREAMD_VOLUME: &str = "nbot_streamd:/etc/nginx/stream.d";
+pub const NGINX_AUTH_VOLUME: &str = "nbot_auth:/etc/nginx/auth";
 pub const NGINX_HTML_VOLUME: &str = "nbot_html:/usr/share/nginx/html";
 pub const NGINX_STATIC_VOLUME: &str = "nbot_static:/static/";
 pub const NGINX_MEDIA_VOLUME: &str = "nbot_media:/media/";
 pub const NGINX_CONTAINER_NAME: &str = "nginx";
 pub const NGINX_IMAGE_NAME: &str = "nbot/nginx";
+/// Copy of the nginx config where changes are validated, outside of the
+/// directories the container watches.
+pub const NGINX_STAGING_DIR: &str = "/etc/nginx/staging";
+/// Label holding the digest of the secret files a container was created with.
+pub const SECRET_FILES_LABEL: &str = "nbot.secret-files";
+pub const VOLUME_HELPER_IMAGE: &str = "busybox:stable";
+/// Label of the helper containers, holding the volume they mount.
+pub const VOLUME_HELPER_LABEL: &str = "nbot.volume-helper";
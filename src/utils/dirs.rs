@@ -37,6 +37,35 @@ impl Dirs {
         format!("{}/config.json", config_dir)
     }
 
+    pub fn secrets_file() -> String {
+        let config_dir = Self::dir();
+        format!("{}/secrets.json", config_dir)
+    }
+
+    pub fn secrets_key_file() -> String {
+        let config_dir = Self::dir();
+        format!("{}/secrets.key", config_dir)
+    }
+
+    pub fn history_file() -> String {
+        let config_dir = Self::dir();
+        format!("{}/history.json", config_dir)
+    }
+
+    pub fn maintenance_file() -> String {
+        let config_dir = Self::dir();
+        format!("{}/maintenance.json", config_dir)
+    }
+
+    pub fn auth_dir() -> String {
+        let auth_dir = format!("{}/auth", Self::dir());
+        if let Err(e) = fs::create_dir_all(&auth_dir) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        auth_dir
+    }
+
     pub fn rm_all() {
         let config_dir = Self::dir();
         let entries = match fs::read_dir(config_dir) {
@@ -0,0 +1,132 @@
+use std::{collections::HashMap, fs};
+
+/// Resolves app environment variables at deploy time.
+///
+/// An entry is either `KEY=VALUE`, where `VALUE` may reference other
+/// variables as `${VAR}` or `${VAR:-default}`, or a bare `KEY`, which passes
+/// the variable through from the host environment. Use `$$` for a literal `$`.
+pub struct Env;
+
+impl Env {
+    /// Resolves the variables from `env_files` followed by `env_vars`. Later
+    /// entries override earlier ones with the same key.
+    pub fn resolve(env_files: &[String], env_vars: &[String]) -> Result<Vec<String>, String> {
+        let mut entries: Vec<String> = vec![];
+        for path in env_files {
+            entries.extend(Self::read_file(path)?);
+        }
+        entries.extend(env_vars.iter().cloned());
+
+        let mut resolved: Vec<(String, String)> = vec![];
+        for entry in entries {
+            let (key, value) = match entry.split_once('=') {
+                Some((key, value)) => {
+                    let known: HashMap<&str, &str> = resolved
+                        .iter()
+                        .map(|(k, v)| (k.as_str(), v.as_str()))
+                        .collect();
+                    (key.to_owned(), Self::interpolate(value, &known)?)
+                }
+                None => match std::env::var(&entry) {
+                    Ok(value) => (entry, value),
+                    Err(_) => {
+                        return Err(format!(
+                            "Environment variable {} is not set on the host",
+                            entry
+                        ))
+                    }
+                },
+            };
+
+            match resolved.iter_mut().find(|(k, _)| *k == key) {
+                Some(existing) => existing.1 = value,
+                None => resolved.push((key, value)),
+            }
+        }
+
+        Ok(resolved
+            .into_iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect())
+    }
+
+    /// Reads a docker-style env file: one `KEY=VALUE` or bare `KEY` per line,
+    /// with blank lines and `#` comments ignored.
+    pub fn read_file(path: &str) -> Result<Vec<String>, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Error reading env file {}: {}", path, e))?;
+
+        let mut entries = vec![];
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+
+            let entry = match line.split_once('=') {
+                Some((key, value)) => format!("{}={}", key.trim(), Self::unquote(value.trim())),
+                None => line.to_owned(),
+            };
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    fn unquote(value: &str) -> &str {
+        for quote in ['"', '\''] {
+            if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+                return &value[1..value.len() - 1];
+            }
+        }
+        value
+    }
+
+    /// Replaces `${VAR}` and `${VAR:-default}` in `value`. Variables are looked
+    /// up in `known` first and in the host environment second.
+    fn interpolate(value: &str, known: &HashMap<&str, &str>) -> Result<String, String> {
+        let mut result = String::new();
+        let mut rest = value;
+
+        while let Some(start) = rest.find('$') {
+            result.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+
+            if let Some(after) = after.strip_prefix('$') {
+                result.push('$');
+                rest = after;
+                continue;
+            }
+
+            let Some(expression) = after.strip_prefix('{') else {
+                result.push('$');
+                rest = after;
+                continue;
+            };
+
+            let Some(end) = expression.find('}') else {
+                return Err(format!("Unterminated variable reference in: {}", value));
+            };
+
+            let (name, default) = match expression[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&expression[..end], None),
+            };
+
+            let found = match known.get(name) {
+                Some(value) => Some(value.to_string()),
+                None => std::env::var(name).ok(),
+            };
+
+            match found.or(default.map(str::to_owned)) {
+                Some(value) => result.push_str(&value),
+                None => return Err(format!("Environment variable {} is not set", name)),
+            }
+
+            rest = &expression[end + 1..];
+        }
+
+        result.push_str(rest);
+        Ok(result)
+    }
+}
@@ -1,5 +1,7 @@
 pub mod contants;
 pub mod dirs;
+pub mod env;
 pub mod networks;
+pub mod shell;
 pub mod tarball;
 pub mod version;
@@ -0,0 +1,212 @@
+use serde::{Deserialize, Deserializer};
+
+/// Splits and quotes command lines with POSIX shell quoting rules, without
+/// any expansion. Used for the cmd and entrypoint of apps, which Docker runs
+/// as an argument vector rather than through a shell.
+pub struct Shell;
+
+impl Shell {
+    /// Splits a command line into its arguments, e.g.
+    /// `sh -c 'echo hello world'` into `sh`, `-c` and `echo hello world`.
+    pub fn split(line: &str) -> Result<Vec<String>, String> {
+        let mut args = vec![];
+        let mut arg = String::new();
+        // Quotes start an argument even if it ends up empty, e.g. ''
+        let mut in_arg = false;
+        let mut chars = line.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                c if c.is_whitespace() => {
+                    if in_arg {
+                        args.push(std::mem::take(&mut arg));
+                        in_arg = false;
+                    }
+                }
+                '\'' => {
+                    in_arg = true;
+                    loop {
+                        match chars.next() {
+                            Some('\'') => break,
+                            Some(c) => arg.push(c),
+                            None => return Err(Self::unterminated(line, "single quote")),
+                        }
+                    }
+                }
+                '"' => {
+                    in_arg = true;
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            // Only these characters can be escaped in double quotes
+                            Some('\\') => match chars.next() {
+                                Some('\n') => {}
+                                Some(c @ ('$' | '`' | '"' | '\\')) => arg.push(c),
+                                Some(c) => {
+                                    arg.push('\\');
+                                    arg.push(c);
+                                }
+                                None => return Err(Self::unterminated(line, "double quote")),
+                            },
+                            Some(c) => arg.push(c),
+                            None => return Err(Self::unterminated(line, "double quote")),
+                        }
+                    }
+                }
+                '\\' => match chars.next() {
+                    Some('\n') => {}
+                    Some(c) => {
+                        in_arg = true;
+                        arg.push(c);
+                    }
+                    None => return Err(format!("Command {} ends with a backslash", line)),
+                },
+                c => {
+                    in_arg = true;
+                    arg.push(c);
+                }
+            }
+        }
+        if in_arg {
+            args.push(arg);
+        }
+
+        Ok(args)
+    }
+
+    /// Joins arguments into a command line that `split` turns back into the
+    /// same arguments. Arguments are quoted only where needed.
+    pub fn join(args: &[String]) -> String {
+        args.iter()
+            .map(|arg| Self::quote(arg))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    fn quote(arg: &str) -> String {
+        let is_safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./_-".contains(c);
+        if !arg.is_empty() && arg.chars().all(is_safe) {
+            return arg.to_owned();
+        }
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+
+    /// Reads an argument vector that is stored either as a list, or as a
+    /// command line. Older versions stored the cmd of an app as a string and
+    /// split it on whitespace, so the line is split the same way, e.g. with
+    /// the unbalanced quote in `echo don't`.
+    pub fn deserialize_args<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Self::deserialize_with(deserializer, |line| {
+            Ok(line.split_whitespace().map(String::from).collect())
+        })
+    }
+
+    /// Reads an argument vector written either as a list, or as a command
+    /// line that is split like a shell does.
+    pub fn deserialize_line<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Self::deserialize_with(deserializer, Self::split)
+    }
+
+    fn deserialize_with<'de, D>(
+        deserializer: D,
+        split: impl Fn(&str) -> Result<Vec<String>, String>,
+    ) -> Result<Option<Vec<String>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Args(Vec<String>),
+            Line(String),
+        }
+
+        match Option::<Stored>::deserialize(deserializer)? {
+            Some(Stored::Args(args)) => Ok(Some(args)),
+            Some(Stored::Line(line)) => split(&line).map(Some).map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+
+    fn unterminated(line: &str, quote: &str) -> String {
+        format!("Command {} has an unterminated {}", line, quote)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn splits_quoted_arguments() {
+        let split = Shell::split("sh -c 'echo hello world'").unwrap();
+        assert_eq!(split, args(&["sh", "-c", "echo hello world"]));
+
+        let split = Shell::split(r#"echo "a \"b\" \n" '' x\ y"#).unwrap();
+        assert_eq!(split, args(&["echo", r#"a "b" \n"#, "", "x y"]));
+
+        let split = Shell::split("  npm   start ").unwrap();
+        assert_eq!(split, args(&["npm", "start"]));
+    }
+
+    #[test]
+    fn rejects_unterminated_quotes() {
+        assert!(Shell::split("echo don't").is_err());
+        assert!(Shell::split(r#"echo "hello"#).is_err());
+        assert!(Shell::split("echo \\").is_err());
+    }
+
+    #[test]
+    fn join_round_trips_through_split() {
+        let cases = [
+            args(&["sh", "-c", "echo 'hello' world"]),
+            args(&["", "KEY=value", "$HOME", "a\"b"]),
+            args(&["node", "server.js"]),
+        ];
+        for case in cases {
+            assert_eq!(Shell::split(&Shell::join(&case)).unwrap(), case);
+        }
+        assert_eq!(Shell::join(&args(&["node", "server.js"])), "node server.js");
+    }
+
+    #[test]
+    fn reads_legacy_lines_like_older_versions() {
+        #[derive(Deserialize)]
+        struct Stored {
+            #[serde(default, deserialize_with = "Shell::deserialize_args")]
+            cmd: Option<Vec<String>>,
+        }
+
+        let stored: Stored = serde_json::from_str(r#"{"cmd": "echo don't"}"#).unwrap();
+        assert_eq!(stored.cmd, Some(args(&["echo", "don't"])));
+
+        let stored: Stored = serde_json::from_str(r#"{"cmd": ["echo", "a b"]}"#).unwrap();
+        assert_eq!(stored.cmd, Some(args(&["echo", "a b"])));
+
+        let stored: Stored = serde_json::from_str("{}").unwrap();
+        assert_eq!(stored.cmd, None);
+    }
+
+    #[test]
+    fn reads_manifest_lines_like_a_shell() {
+        #[derive(Deserialize)]
+        struct Manifest {
+            #[serde(default, deserialize_with = "Shell::deserialize_line")]
+            cmd: Option<Vec<String>>,
+        }
+
+        let manifest: Manifest = serde_json::from_str(r#"{"cmd": "sh -c 'echo hi'"}"#).unwrap();
+        assert_eq!(manifest.cmd, Some(args(&["sh", "-c", "echo hi"])));
+        assert!(serde_json::from_str::<Manifest>(r#"{"cmd": "echo don't"}"#).is_err());
+    }
+}
@@ -5,6 +5,13 @@ pub struct Tarball;
 
 impl Tarball {
     pub fn create(files: Vec<(&str, &str)>) -> Result<Vec<u8>, std::io::Error> {
+        Self::create_with_mode(files, 0o644)
+    }
+
+    pub fn create_with_mode(
+        files: Vec<(&str, &str)>,
+        mode: u32,
+    ) -> Result<Vec<u8>, std::io::Error> {
         let buffer = Vec::new();
         let cursor = Cursor::new(buffer);
         let mut tar_builder = Builder::new(cursor);
@@ -15,7 +22,8 @@ impl Tarball {
                 eprintln!("Error setting path for file: {}", file);
                 std::process::exit(1);
             }
-            header.set_size(content.as_bytes().len() as u64);
+            header.set_size(content.len() as u64);
+            header.set_mode(mode);
             header.set_cksum();
             tar_builder.append(&header, content.as_bytes())?;
         }
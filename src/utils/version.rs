@@ -10,7 +10,7 @@ impl Version {
             .find(|line| line.starts_with("version"))
             .unwrap()
             .split('=')
-            .last()
+            .next_back()
             .unwrap()
             .trim()
             .replace('"', "");